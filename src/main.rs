@@ -1,36 +1,317 @@
-mod plang;
-use plang::{ Lexer }; 
+use std::{ env, fs, panic, process, thread };
+use std::io::{ self, IsTerminal, Read, Write };
+use plang_rust::plang::{ self, highlight, html, structogram, Lexer, Parser, Environment, STACK_SIZE, Lang, Severity, Program, Diagnostics, NumberPolicy, Dialect, Source, Encoding };
 
-fn main() {
-    let script = r#"** this is a commentá
-        ** so 12312 is :=== this
-        PROGRÁM a
+const USAGE: &str = "\
+usage: plang <command> [options] [file]
+
+commands:
+    run       check the program, then run it
+    check     parse and type check the program, exit with 1 on errors
+    tokens    list the tokens of the program with their positions
+              and byte offsets in the file
+    render    print the program as a document
+    cat       print the program with syntax highlighting
+
+options:
+    --format <html|svg|ansi>
+                           format of `render`: HTML listing or structogram
+                           (default: html); of `cat`: HTML or terminal
+                           colors (default: colors on a terminal that
+                           doesn't set NO_COLOR, plain text otherwise)
+    --lang <hu|en>         language of the messages (default: $PLANG_LANG or hu)
+    --dialect <hu|en>      language of the keywords, e.g. `HA` or `IF`
+                           (default: hu)
+    --numbers <dot|comma|hu>
+                           decimal separator: `3.14`, `3,14`, or either with
+                           `,` separating arguments inside parentheses
+                           (default: hu for the hu dialect, dot for en)
+    --encoding <utf-8|latin2|cp1250>
+                           encoding of the file (default: UTF-8 if it's
+                           valid UTF-8, otherwise guessed)
+    -h, --help             show this help
+
+The program is read from standard input if no file, or `-` is given.
+`run` then has no input left for BE.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Tokens,
+    Render,
+    Cat
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Html,
+    Svg,
+    Ansi
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    command: Command,
+    /// `None` for the default of the command
+    format: Option<Format>,
+    /// `None` for standard input
+    path: Option<String>,
+    lang: Lang,
+    dialect: Dialect,
+    /// Overrides the number format of the dialect
+    numbers: Option<NumberPolicy>,
+    /// `None` to detect it
+    encoding: Option<Encoding>
+}
+
+impl Options {
+    fn dialect(&self) -> Dialect {
+        match self.numbers {
+            Some(numbers) => self.dialect.clone().with_numbers(numbers),
+            None => self.dialect.clone()
+        }
+    }
+}
+
+/// Exit codes: the program has errors, or we couldn't even get to it.
+const EXIT_ERRORS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I, lang: Lang) -> Result<Options, String> {
+    let command = match args.next().as_deref() {
+        Some("run") => Command::Run,
+        Some("check") => Command::Check,
+        Some("tokens") => Command::Tokens,
+        Some("render") => Command::Render,
+        Some("cat") => Command::Cat,
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+        None => return Err(String::from("no command given"))
+    };
+
+    let mut opts = Options { command, format: None, path: None, lang,
+        dialect: Dialect::default(), numbers: None, encoding: None };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => {
+                let name = args.next().unwrap_or_default();
+                opts.lang = match Lang::from_name(&name) {
+                    Some(lang) => lang,
+                    None => return Err(format!("unknown language `{}`", name))
+                };
+            },
+            "--format" => {
+                opts.format = match args.next().as_deref() {
+                    Some("html") => Some(Format::Html),
+                    Some("svg") => Some(Format::Svg),
+                    Some("ansi") => Some(Format::Ansi),
+                    Some(other) => return Err(format!("unknown format `{}`", other)),
+                    None => return Err(String::from("no format given"))
+                };
+            },
+            "--dialect" => {
+                let name = args.next().unwrap_or_default();
+                opts.dialect = match Dialect::from_name(&name) {
+                    Some(dialect) => dialect,
+                    None => return Err(format!("unknown dialect `{}`", name))
+                };
+            },
+            "--numbers" => {
+                opts.numbers = match args.next().as_deref() {
+                    Some("dot") => Some(NumberPolicy::Dot),
+                    Some("comma") => Some(NumberPolicy::Comma),
+                    Some("hu") => Some(NumberPolicy::Hungarian),
+                    Some(other) => return Err(format!("unknown number format `{}`", other)),
+                    None => return Err(String::from("no number format given"))
+                };
+            },
+            "--encoding" => {
+                let name = args.next().unwrap_or_default();
+                opts.encoding = match Encoding::from_name(&name) {
+                    Some(encoding) => Some(encoding),
+                    None => return Err(format!("unknown encoding `{}`", name))
+                };
+            },
+            "-" => opts.path = None,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if opts.path.is_some() => return Err(String::from("more than one file given")),
+            _ => opts.path = Some(arg)
+        }
+    }
+
+    match (opts.command, opts.format) {
+        (Command::Render, Some(Format::Ansi)) => Err(String::from("`render` has no `ansi` format")),
+        (Command::Cat, Some(Format::Svg)) => Err(String::from("`cat` has no `svg` format")),
+        _ => Ok(opts)
+    }
+}
+
+fn read_source(path: &Option<String>, encoding: Option<Encoding>) -> io::Result<Source> {
+    let bytes = match path {
+        Some(path) => fs::read(path)?,
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+    Source::decode(&bytes, encoding).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Parses and checks the program, printing everything found.
+fn check(src: &str, path: &str, opts: &Options) -> Option<Program> {
+    let (program, mut diags) = Parser::new(Lexer::with_dialect(src, opts.dialect())).parse_with_diagnostics();
+    if let Some(p) = &program {
+        plang::check(p, &mut diags);
+    }
+    print_diagnostics(&diags, src, path, opts.lang);
+
+    match program {
+        Some(p) if !diags.has_errors() => Some(p),
+        _ => None
+    }
+}
 
-        VALTOZOK:
-        x: SZOVEG
-        k: KARAKTER
-        e1: EGESZ
+fn print_diagnostics(diags: &Diagnostics, src: &str, path: &str, lang: Lang) {
+    if !diags.is_empty() {
+        eprintln!("{}", diags.render(src, path, lang));
+    }
+}
 
-        x:=" vmi szöveg \u644  ...ja"
-        e1 :=123.0.0,5
-        k := "a"
-        c :='a'
-        _asd :== 123213
+fn execute(opts: &Options) -> i32 {
+    let source = match read_source(&opts.path, opts.encoding) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: {}", opts.path.as_deref().unwrap_or("-"), e);
+            return EXIT_USAGE;
+        }
+    };
+    let src = source.text();
+    let path = opts.path.as_deref().unwrap_or("<stdin>");
+    let lang = opts.lang;
 
-        ___a__\u6653
+    match opts.command {
+        Command::Tokens => {
+            let mut lx = Lexer::with_dialect(src, opts.dialect());
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            for t in lx.by_ref() {
+                // bytes of the file, which differ from the text in other
+                // encodings or with `\r\n` line endings
+                let span = source.original_span(t.tok.span);
+                // stop quietly if the reader went away, like `plang tokens | head`
+                if writeln!(out, "{:?} {}..{}", t, span.begin, span.end).is_err() {
+                    break;
+                }
+            }
+            let mut diags = Diagnostics::new();
+            diags.extend(lx.take_diagnostics());
+            print_diagnostics(&diags, src, path, lang);
+            if diags.has_errors() { EXIT_ERRORS } else { 0 }
+        },
+        Command::Check => match check(src, path, opts) {
+            Some(_) => 0,
+            None => EXIT_ERRORS
+        },
+        Command::Run => {
+            let program = match check(src, path, opts) {
+                Some(p) => p,
+                None => return EXIT_ERRORS
+            };
+            // on a thread of its own, as the main one's stack may be too
+            // small for the calls the interpreter allows
+            let result = thread::scope(|s| {
+                let runner = thread::Builder::new()
+                    .stack_size(STACK_SIZE)
+                    .spawn_scoped(s, || Environment::new().run(&program));
+                match runner {
+                    Ok(runner) => runner.join().unwrap_or_else(|e| panic::resume_unwind(e)),
+                    Err(e) => Err(e.into())
+                }
+            });
+            match result {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("{}: {}[{}]: {}", path, Severity::Error.name(lang), e.code().as_str(), e.message(lang));
+                    EXIT_ERRORS
+                }
+            }
+        },
+        Command::Render => {
+            let program = match check(src, path, opts) {
+                Some(p) => p,
+                None => return EXIT_ERRORS
+            };
+            match opts.format {
+                Some(Format::Svg) => print!("{}", structogram::render_svg(&program)),
+                _ => print!("{}", html::render_page(&program))
+            }
+            0
+        },
+        Command::Cat => {
+            let lx = Lexer::with_dialect(src, opts.dialect());
+            let colors = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+            let out = match opts.format {
+                Some(Format::Html) => highlight::render_page(lx, path),
+                Some(Format::Ansi) => highlight::ansi(lx),
+                _ if colors => highlight::ansi(lx),
+                _ => src.to_string()
+            };
+            // like `tokens`, a closed pipe is not an error
+            let _ = io::stdout().lock().write_all(out.as_bytes());
+            0
+        }
+    }
+}
 
-        i32 := 1024 
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
 
-        PROGRÁM_VÉGE
-    "#;
+    match parse_args(args.into_iter(), Lang::from_env()) {
+        Ok(opts) => process::exit(execute(&opts)),
+        Err(msg) => {
+            eprintln!("plang: {}\n\n{}", msg, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
 
-    let lx = Lexer::from(script);
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // for t in lx.into_iter().filter(|t| t.tok.ttype == TokenType::Undefined) {
-    //     println!("{:?}", t);
-    // }
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|a| a.to_string()), Lang::Hu)
+    }
 
-    for t in lx {
-        println!("{:?}", t);
+    #[test]
+    fn arguments() {
+        assert_eq!(parse(&["run", "a.plang"]), Ok(Options {
+            command: Command::Run, format: None, path: Some(String::from("a.plang")), lang: Lang::Hu,
+            dialect: Dialect::hungarian(), numbers: None, encoding: None
+        }));
+        assert_eq!(parse(&["check", "--lang", "en", "--numbers", "dot", "-"]), Ok(Options {
+            command: Command::Check, format: None, path: None, lang: Lang::En,
+            dialect: Dialect::hungarian(), numbers: Some(NumberPolicy::Dot), encoding: None
+        }));
+        assert_eq!(parse(&["run", "--encoding", "latin2"]).unwrap().encoding, Some(Encoding::Latin2));
+        let opts = parse(&["run", "--numbers", "comma", "--dialect", "en"]).unwrap();
+        assert_eq!(opts.dialect(), Dialect::english().with_numbers(NumberPolicy::Comma));
+        assert!(parse(&["tokens"]).is_ok());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["build"]).is_err());
+        assert!(parse(&["run", "--lang", "de"]).is_err());
+        assert!(parse(&["run", "a", "b"]).is_err());
+        assert!(parse(&["run", "--numbers", "hex"]).is_err());
+        assert!(parse(&["run", "--dialect", "de"]).is_err());
+        assert!(parse(&["run", "--encoding", "koi8-r"]).is_err());
+        assert_eq!(parse(&["cat", "--format", "ansi", "a"]).unwrap().format, Some(Format::Ansi));
+        assert!(parse(&["cat", "--format", "svg"]).is_err());
+        assert!(parse(&["render", "--format", "ansi"]).is_err());
     }
 }
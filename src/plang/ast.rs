@@ -0,0 +1,219 @@
+use super::unary_operator::UnaryOperator;
+use super::binary_operator::{ BinaryOperator, Assoc };
+use super::declarations::Declarations;
+use super::variable::TypeName;
+use super::tokenizer::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Char(char),
+    Bool(bool)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    Variable(String),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>)
+}
+
+/// Like tokens, expressions compare equal by content, regardless of 
+/// where they are in the source.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExprKind,
+    pub span: Span
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Expression {
+    pub fn new(kind: ExprKind, span: Span) -> Expression {
+        Expression { kind, span }
+    }
+
+    /// True if the expression can stand on the left side of `:=`.
+    pub fn is_assignable(&self) -> bool {
+        match &self.kind {
+            ExprKind::Variable(_) => true,
+            ExprKind::Binary(BinaryOperator::Bracket, a, _) => a.is_assignable(),
+            _ => false
+        }
+    }
+
+    /// Precedence of the outermost operator; values, calls and the
+    /// enclosing `| |` never need parentheses around them.
+    pub fn precedence(&self) -> u8 {
+        match &self.kind {
+            ExprKind::Unary(UnaryOperator::Pipe, _) => u8::MAX,
+            ExprKind::Unary(_, _) => UnaryOperator::PRECEDENCE,
+            ExprKind::Binary(op, _, _) => op.precedence(),
+            _ => u8::MAX
+        }
+    }
+
+    /// HTML text of the expression, built from the operators' and values'
+    /// own renderers, with only the parentheses needed to parse it back.
+    pub fn render(&self) -> String {
+        match &self.kind {
+            ExprKind::Literal(lit) => escape_html(&lit.value().render()),
+            ExprKind::Variable(name) => escape_html(name),
+            ExprKind::Unary(UnaryOperator::Pipe, a) => UnaryOperator::Pipe.render(a.render()),
+            ExprKind::Unary(op, a) => op.render(a.render_within(a.precedence() < UnaryOperator::PRECEDENCE)),
+            // indexing chains, as in `m[i][j]`
+            ExprKind::Binary(BinaryOperator::Bracket, a, b) => {
+                let parens = a.precedence() < BinaryOperator::Bracket.precedence();
+                BinaryOperator::Bracket.render(a.render_within(parens), b.render())
+            },
+            ExprKind::Binary(op, a, b) => {
+                let prec = op.precedence();
+                let left = match a.kind {
+                    ExprKind::Unary(_, _) => a.precedence() < prec,
+                    _ => a.precedence() < prec || (a.precedence() == prec && op.assoc() != Assoc::Left)
+                };
+                // a prefix operator can only start an operand, so it's
+                // never ambiguous on the right
+                let right = match b.kind {
+                    ExprKind::Unary(_, _) => false,
+                    _ => b.precedence() < prec || (b.precedence() == prec && op.assoc() != Assoc::Right)
+                };
+                op.render(a.render_within(left), b.render_within(right))
+            },
+            ExprKind::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.render()).collect();
+                format!("{}({})", escape_html(name), args.join(", "))
+            }
+        }
+    }
+
+    fn render_within(&self, parens: bool) -> String {
+        if parens {
+            format!("({})", self.render())
+        } else {
+            self.render()
+        }
+    }
+}
+
+/// Escapes text to be put between HTML tags.
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c)
+        }
+    }
+    out
+}
+
+impl From<ExprKind> for Expression {
+    fn from(kind: ExprKind) -> Expression {
+        Expression { kind, span: Span::default() }
+    }
+}
+
+pub type Block = Vec<Statement>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// `target := value`
+    Assign(Expression, Expression),
+
+    /// `HA c1 AKKOR ... KÜLÖNBEN HA c2 AKKOR ... KÜLÖNBEN ... HA_VÉGE`
+    If {
+        branches: Vec<(Expression, Block)>,
+        otherwise: Option<Block>
+    },
+
+    /// `CIKLUS AMÍG cond ... CIKLUS_VÉGE`
+    While(Expression, Block),
+
+    /// Procedure call, `name(args)`, always an `ExprKind::Call`
+    Call(Expression),
+
+    /// `KI: a, b, ...`
+    Output(Vec<Expression>),
+
+    /// `BE: a, b, ...`
+    Input(Vec<Expression>)
+}
+
+/// How an argument is passed to a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamMode {
+    /// The value of the argument
+    #[default]
+    In,
+    /// `KI`: the parameter starts from its initial value, and is given
+    /// back to the argument variable on return
+    Out,
+    /// `CÍM`: the argument variable itself, changes are seen by the caller
+    Ref
+}
+
+impl ParamMode {
+    /// Keyword written before the parameter names, if any.
+    pub const fn keyword(&self) -> Option<&'static str> {
+        match self {
+            ParamMode::In => None,
+            ParamMode::Out => Some("KI"),
+            ParamMode::Ref => Some("CÍM")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ptype: TypeName,
+    pub mode: ParamMode,
+    pub span: Span
+}
+
+/// An `ELJÁRÁS` or, if it has a result type, a `FÜGGVÉNY`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subprogram {
+    pub name: String,
+    /// Where the name is in the definition
+    pub span: Span,
+    pub params: Vec<Param>,
+    pub result: Option<TypeName>,
+    pub decls: Declarations,
+    pub body: Block,
+    /// Doc comment written before the definition
+    pub doc: Option<String>
+}
+
+impl Subprogram {
+    pub fn is_function(&self) -> bool {
+        self.result.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub name: String,
+    pub decls: Declarations,
+    pub body: Block,
+    pub subprograms: Vec<Subprogram>,
+    /// Doc comment written before `PROGRAM`, like the exercise
+    pub doc: Option<String>
+}
+
+impl Program {
+    pub fn subprogram(&self, name: &str) -> Option<&Subprogram> {
+        self.subprograms.iter().find(|s| s.name == name)
+    }
+}
@@ -1,4 +1,13 @@
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+    /// `a < b < c` is an error
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinaryOperator {
     Plus, Minus, Star, Slash,
     Hat, At, Lt, Gt, Eq, Ne,
@@ -7,6 +16,30 @@ pub enum BinaryOperator {
 }
 
 impl BinaryOperator {
+    /// Looks up the operator by its symbol, or by the lexical 
+    /// (lowercase, deaccented) form if it is written as a keyword.
+    pub fn from_lexical(lex: &str) -> Option<BinaryOperator> {
+        match lex {
+            "+" => Some(BinaryOperator::Plus),
+            "-" => Some(BinaryOperator::Minus),
+            "*" => Some(BinaryOperator::Star),
+            "/" => Some(BinaryOperator::Slash),
+            "^" => Some(BinaryOperator::Hat),
+            "@" => Some(BinaryOperator::At),
+            "<" => Some(BinaryOperator::Lt),
+            ">" => Some(BinaryOperator::Gt),
+            "=" => Some(BinaryOperator::Eq),
+            "/=" => Some(BinaryOperator::Ne),
+            "<=" => Some(BinaryOperator::Le),
+            ">=" => Some(BinaryOperator::Ge),
+            "es" => Some(BinaryOperator::And),
+            "vagy" => Some(BinaryOperator::Or),
+            "div" => Some(BinaryOperator::Div),
+            "mod" => Some(BinaryOperator::Mod),
+            _ => None
+        }
+    }
+
     pub const fn op(&self) -> &'static str {
         match self {
             BinaryOperator::Plus => "+",
@@ -29,6 +62,30 @@ impl BinaryOperator {
         }
     }
 
+    /// Binding strength, higher binds tighter. Prefix operators are
+    /// between `*` and `^`, see `UnaryOperator::PRECEDENCE`.
+    pub const fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Lt | BinaryOperator::Gt | BinaryOperator::Eq |
+            BinaryOperator::Ne | BinaryOperator::Le | BinaryOperator::Ge => 3,
+            BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::At => 4,
+            BinaryOperator::Star | BinaryOperator::Slash |
+            BinaryOperator::Div | BinaryOperator::Mod => 5,
+            BinaryOperator::Hat => 7,
+            BinaryOperator::Bracket => 8
+        }
+    }
+
+    pub const fn assoc(&self) -> Assoc {
+        match self.precedence() {
+            3 => Assoc::None,
+            7 => Assoc::Right,
+            _ => Assoc::Left
+        }
+    }
+
     pub fn render(&self, a: String /* Expression */, b: String /* Expression */) -> String {
         match self {
             BinaryOperator::Lt => a + " &lt; " + &b,
@@ -36,7 +93,7 @@ impl BinaryOperator {
             BinaryOperator::Le => a + " &lt;= " + &b,
             BinaryOperator::Ge => a + " &gt;= " + &b,
             BinaryOperator::Bracket => a + "[" + &b + "]",
-            _ => a + " " + &self.op() + " " + &b
+            _ => a + " " + self.op() + " " + &b
         }
     }
 }
\ No newline at end of file
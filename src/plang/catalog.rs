@@ -0,0 +1,318 @@
+use std::env;
+use super::diagnostic::{ Code, Severity };
+
+/// Language of the messages shown to the user. PLang is taught in
+/// Hungarian, so that's the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Hu,
+    En
+}
+
+impl Lang {
+    pub fn from_name(name: &str) -> Option<Lang> {
+        match name.to_lowercase().as_str() {
+            "hu" | "magyar" | "hungarian" => Some(Lang::Hu),
+            "en" | "angol" | "english" => Some(Lang::En),
+            _ => None
+        }
+    }
+
+    /// Language set in the `PLANG_LANG` environment variable, or the default.
+    pub fn from_env() -> Lang {
+        env::var("PLANG_LANG").ok()
+            .and_then(|name| Lang::from_name(&name))
+            .unwrap_or_default()
+    }
+}
+
+/// Fixed pieces of text used in diagnostics besides the main message:
+/// labels, notes and arguments that need translating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phrase {
+    // things expected or found
+    Name,
+    Expression,
+    Statement,
+    EndOfLine,
+    EndOfFile,
+
+    // labels
+    ExpectedHere,
+    DeclaredAgain,
+    FirstDeclaredHere,
+    ParamDeclaredHere,
+    FirstDefinedHere,
+    DefinedHere,
+    BlockOpenedHere,
+    InsideBlock,
+    AssignedToThis,
+    NeedsVariable,
+    CharCount,
+    ExtraSeparator,
+    ExponentDigits,
+    NumberSuffix,
+    NumberTooLarge,
+    AsciiDigits,
+    DecimalSeparator,
+    WrittenDifferently,
+
+    // notes
+    DeclSyntax,
+    TypeList,
+    UseString,
+    CombiningMark
+}
+
+impl Phrase {
+    pub const fn template(&self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::Hu => match self {
+                Phrase::Name => "név",
+                Phrase::Expression => "kifejezés",
+                Phrase::Statement => "utasítás",
+                Phrase::EndOfLine => "sor vége",
+                Phrase::EndOfFile => "fájl vége",
+
+                Phrase::ExpectedHere => "várt: {0}",
+                Phrase::DeclaredAgain => "újra deklarálva",
+                Phrase::FirstDeclaredHere => "először itt deklarálva",
+                Phrase::ParamDeclaredHere => "a paraméter itt van deklarálva",
+                Phrase::FirstDefinedHere => "először itt definiálva",
+                Phrase::DefinedHere => "itt van definiálva",
+                Phrase::BlockOpenedHere => "a blokk itt kezdődik",
+                Phrase::InsideBlock => "az itt kezdődő blokkon belül",
+                Phrase::AssignedToThis => "ez kapja az értéket",
+                Phrase::NeedsVariable => "a BE ide változót vár",
+                Phrase::CharCount => "{0} karakter",
+                Phrase::ExtraSeparator => "egynél több tizedesjel",
+                Phrase::ExponentDigits => "a kitevőből hiányoznak a számjegyek",
+                Phrase::NumberSuffix => "a szám után közvetlenül nem állhat betű",
+                Phrase::NumberTooLarge => "túl nagy szám",
+                Phrase::AsciiDigits => "a számok a 0–9 számjegyekkel írhatók",
+                Phrase::DecimalSeparator => "a tizedesjel {0}",
+                Phrase::WrittenDifferently => "itt másképp van kódolva",
+
+                Phrase::DeclSyntax => "a változók deklarálása: `név, név: TÍPUS`",
+                Phrase::TypeList => "a típusok: EGÉSZ, VALÓS, SZÖVEG, KARAKTER, LOGIKAI és TÖMB[méret: TÍPUS]",
+                Phrase::UseString => "több karakterhez szöveg literál kell: \"...\"",
+                Phrase::CombiningMark => "az ékezet külön karakterként van kódolva, az ékezetes betűt egyben kell beírni"
+            },
+            Lang::En => match self {
+                Phrase::Name => "a name",
+                Phrase::Expression => "an expression",
+                Phrase::Statement => "a statement",
+                Phrase::EndOfLine => "end of line",
+                Phrase::EndOfFile => "end of file",
+
+                Phrase::ExpectedHere => "expected {0}",
+                Phrase::DeclaredAgain => "declared again",
+                Phrase::FirstDeclaredHere => "first declared here",
+                Phrase::ParamDeclaredHere => "parameter declared here",
+                Phrase::FirstDefinedHere => "first defined here",
+                Phrase::DefinedHere => "defined here",
+                Phrase::BlockOpenedHere => "block opened here",
+                Phrase::InsideBlock => "inside the block opened here",
+                Phrase::AssignedToThis => "assigned to this",
+                Phrase::NeedsVariable => "BE needs a variable here",
+                Phrase::CharCount => "{0} characters",
+                Phrase::ExtraSeparator => "more than one decimal separator",
+                Phrase::ExponentDigits => "the exponent has no digits",
+                Phrase::NumberSuffix => "letters can't follow a number directly",
+                Phrase::NumberTooLarge => "number too large",
+                Phrase::AsciiDigits => "numbers are written with the digits 0-9",
+                Phrase::DecimalSeparator => "the decimal separator is {0}",
+                Phrase::WrittenDifferently => "encoded differently here",
+
+                Phrase::DeclSyntax => "variables are declared as `name, name: TYPE`",
+                Phrase::TypeList => "the types are EGÉSZ, VALÓS, SZÖVEG, KARAKTER, LOGIKAI and TÖMB[size: TYPE]",
+                Phrase::UseString => "text of more characters needs a string literal: \"...\"",
+                Phrase::CombiningMark => "the accent is encoded as a separate character, type the accented letter as one"
+            }
+        }
+    }
+}
+
+impl Severity {
+    pub const fn name(&self, lang: Lang) -> &'static str {
+        match (self, lang) {
+            (Severity::Error, Lang::Hu) => "hiba",
+            (Severity::Error, Lang::En) => "error",
+            (Severity::Warning, Lang::Hu) => "figyelmeztetés",
+            (Severity::Warning, Lang::En) => "warning"
+        }
+    }
+}
+
+/// Word introducing the notes under a diagnostic.
+pub const fn note_name(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Hu => "megjegyzés",
+        Lang::En => "note"
+    }
+}
+
+impl Code {
+    /// Message with `{0}`, `{1}`... standing for the diagnostic's arguments.
+    pub const fn template(&self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::Hu => self.template_hu(),
+            Lang::En => self.template_en()
+        }
+    }
+
+    const fn template_hu(&self) -> &'static str {
+        match self {
+            Code::UnterminatedLiteral => "lezáratlan literál",
+            Code::InvalidChar => "a karakter literálnak pontosan egy karaktert kell tartalmaznia",
+            Code::InvalidNumber => "érvénytelen szám: `{0}`",
+            Code::InvalidWord => "`{0}` nem érvényes név",
+            Code::InvalidEscape => "érvénytelen escape szekvencia: `{0}`",
+            Code::UnterminatedComment => "lezáratlan megjegyzés, hiányzik a `{0}`",
+
+            Code::UnknownOperator => "ismeretlen operátor: `{0}`",
+            Code::MixedForms => "a(z) `{0}` név különböző Unicode-formákban van leírva",
+
+            Code::Expected => "várt: {0}, talált: {1}",
+            Code::DeclName => "várt: változónév, talált: {0}",
+            Code::DeclColon => "a változónevek után `:` kell, talált: {0}",
+            Code::DeclType => "várt: típus, talált: {0}",
+            Code::DupName => "`{0}` már deklarálva van",
+            Code::NotAssignable => "csak változónak lehet értéket adni",
+            Code::MissingProgram => "hiányzik a PROGRAM blokk",
+            Code::DupProgram => "egynél több PROGRAM blokk",
+            Code::DupSubprogram => "`{0}` már definiálva van",
+            Code::Unexpected => "nem várt elem: {0}",
+            Code::ArraySize => "a tömb mérete pozitív egész szám kell legyen, talált: {0}",
+
+            Code::Undeclared => "nem deklarált változó: `{0}`",
+            Code::Mismatch => "várt típus: {0}, kapott: {1}",
+            Code::UnaryOperator => "a(z) `{0}` operátor nem alkalmazható erre: {1}",
+            Code::BinaryOperator => "a(z) `{0}` operátor nem alkalmazható ezekre: {1} és {2}",
+            Code::Condition => "a feltétel típusa LOGIKAI kell legyen, nem {0}",
+            Code::UnknownSubprogram => "`{0}` nincs definiálva",
+            Code::Arity => "`{0}` {1} paramétert vár, de {2} argumentumot kapott",
+            Code::NotFunction => "`{0}` eljárás, nincs értéke",
+            Code::NotReference => "a(z) `{0}` paraméternek változót kell átadni",
+
+            Code::Operator => "a(z) `{0}` operátor nem alkalmazható erre: {1}",
+            Code::DivByZero => "osztás nullával",
+            Code::Overflow => "túlcsordulás",
+            Code::Domain => "a(z) `{0}` argumentuma kívül esik az értelmezési tartományán",
+            Code::Input => "`{0}` nem érvényes {1} érték",
+            Code::Io => "I/O hiba: {0}",
+            Code::Unsupported => "nem támogatott: {0}",
+            Code::Index => "a(z) {0} index kívül esik a tömbön, az indexek: 1..{1}",
+            Code::Recursion => "túl mély rekurzió, {0} hívás van folyamatban"
+        }
+    }
+
+    const fn template_en(&self) -> &'static str {
+        match self {
+            Code::UnterminatedLiteral => "unterminated literal",
+            Code::InvalidChar => "character literal must contain exactly one character",
+            Code::InvalidNumber => "invalid number `{0}`",
+            Code::InvalidWord => "`{0}` is not a valid name",
+            Code::InvalidEscape => "invalid escape sequence `{0}`",
+            Code::UnterminatedComment => "unterminated comment, `{0}` is missing",
+
+            Code::UnknownOperator => "unknown operator `{0}`",
+            Code::MixedForms => "the name `{0}` is written in different Unicode forms",
+
+            Code::Expected => "expected {0}, found {1}",
+            Code::DeclName => "expected a variable name, found {0}",
+            Code::DeclColon => "expected `:` after the variable names, found {0}",
+            Code::DeclType => "expected a type, found {0}",
+            Code::DupName => "`{0}` is already declared",
+            Code::NotAssignable => "only a variable can be assigned to",
+            Code::MissingProgram => "no PROGRAM block found",
+            Code::DupProgram => "more than one PROGRAM block",
+            Code::DupSubprogram => "`{0}` is already defined",
+            Code::Unexpected => "unexpected {0}",
+            Code::ArraySize => "the size of an array must be a positive whole number, found {0}",
+
+            Code::Undeclared => "undeclared variable `{0}`",
+            Code::Mismatch => "expected {0}, found {1}",
+            Code::UnaryOperator => "operator `{0}` cannot be applied to {1}",
+            Code::BinaryOperator => "operator `{0}` cannot be applied to {1} and {2}",
+            Code::Condition => "condition must be LOGIKAI, found {0}",
+            Code::UnknownSubprogram => "`{0}` is not defined",
+            Code::Arity => "`{0}` takes {1} argument(s) but {2} were given",
+            Code::NotFunction => "`{0}` is a procedure, it has no value",
+            Code::NotReference => "parameter `{0}` needs a variable as its argument",
+
+            Code::Operator => "operator `{0}` cannot be applied to {1}",
+            Code::DivByZero => "division by zero",
+            Code::Overflow => "arithmetic overflow",
+            Code::Domain => "argument of `{0}` is out of its domain",
+            Code::Input => "`{0}` is not a valid {1} value",
+            Code::Io => "I/O error: {0}",
+            Code::Unsupported => "{0} is not supported",
+            Code::Index => "index {0} is out of bounds, valid indices are 1..{1}",
+            Code::Recursion => "recursion too deep, {0} calls are in progress"
+        }
+    }
+}
+
+/// Fills the `{n}` placeholders of a template. Only the template is
+/// scanned, so an argument containing `{1}` is left as it is.
+pub fn format_template(template: &str, args: &[String]) -> String {
+    let mut s = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        s.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let arg = after.find('}')
+            .and_then(|close| after[..close].parse::<usize>().ok().map(|n| (n, close)))
+            .and_then(|(n, close)| args.get(n).map(|arg| (arg, close)));
+        match arg {
+            Some((arg, close)) => {
+                s.push_str(arg);
+                rest = &after[close + 1..];
+            },
+            None => {
+                s.push('{');
+                rest = after;
+            }
+        }
+    }
+    s.push_str(rest);
+    s
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both catalogs must use the same placeholders for a message, or
+    /// arguments would go missing in one language.
+    #[test]
+    fn same_placeholders() {
+        let placeholders = |s: &str| (0..4)
+            .filter(|i| s.contains(&format!("{{{}}}", i)))
+            .collect::<Vec<_>>();
+
+        for code in Code::ALL {
+            assert_eq!(Code::from_code(code.as_str()), Some(code));
+            assert_eq!(placeholders(code.template(Lang::Hu)), placeholders(code.template(Lang::En)), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let args = [String::from("{1}"), String::from("b")];
+        assert_eq!(format_template("{0} és {1}", &args), "{1} és b");
+        assert_eq!(format_template("{1}{0}{2} {x}", &args), "b{1}{2} {x}");
+        assert_eq!(format_template("`{` {0", &args), "`{` {0");
+    }
+
+    #[test]
+    fn lang_names() {
+        assert_eq!(Lang::default(), Lang::Hu);
+        assert_eq!(Lang::from_name("EN"), Some(Lang::En));
+        assert_eq!(Lang::from_name("magyar"), Some(Lang::Hu));
+        assert_eq!(Lang::from_name("de"), None);
+    }
+}
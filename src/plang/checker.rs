@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use super::ast::*;
+use super::declarations::Declarations;
+use super::variable::{ Type, TypeName };
+use super::binary_operator::BinaryOperator;
+use super::diagnostic::{ Diagnostic, Diagnostics, Code, Message };
+use super::catalog::Phrase;
+
+/// Checks that every name is declared and every operator, assignment,
+/// condition and call gets values of fitting types, before running.
+pub fn check(program: &Program, diags: &mut Diagnostics) {
+    let mut checker = Checker { program, scope: HashMap::new(), diags };
+
+    checker.enter(&program.decls);
+    checker.check_block(&program.body);
+
+    for sub in &program.subprograms {
+        checker.scope.clear();
+        for p in &sub.params {
+            checker.scope.insert(p.name.clone(), p.ptype.clone());
+        }
+        // inside a function its name is the variable holding the result
+        if let Some(t) = &sub.result {
+            checker.scope.insert(sub.name.clone(), t.clone());
+        }
+        checker.enter(&sub.decls);
+        checker.check_block(&sub.body);
+    }
+}
+
+struct Checker<'a> {
+    program: &'a Program,
+    /// Variables visible in the block being checked. Subprograms only
+    /// see their own parameters and locals.
+    scope: HashMap<String, TypeName>,
+    diags: &'a mut Diagnostics
+}
+
+impl Checker<'_> {
+    fn enter(&mut self, decls: &Declarations) {
+        for (name, vtype) in decls.names() {
+            self.scope.insert(name.clone(), vtype.clone());
+        }
+    }
+
+    fn check_block(&mut self, block: &[Statement]) {
+        for s in block {
+            self.check_statement(s);
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Assign(target, value) => {
+                let t = self.type_of(target);
+                let v = self.type_of(value);
+                if let (Some(t), Some(v)) = (t, v) {
+                    if !t.can_copy(v.as_ref()) {
+                        let expected = t.type_name().name();
+                        let d = Diagnostic::error(Code::Mismatch, value.span, &[&expected, &v.type_name().name()])
+                            .with_label(Message::new(Phrase::ExpectedHere, expected.as_str()))
+                            .with_secondary(target.span, Phrase::AssignedToThis);
+                        self.diags.push(d);
+                    }
+                }
+            },
+            Statement::If { branches, otherwise } => {
+                for (cond, body) in branches {
+                    self.check_cond(cond);
+                    self.check_block(body);
+                }
+                if let Some(body) = otherwise {
+                    self.check_block(body);
+                }
+            },
+            Statement::While(cond, body) => {
+                self.check_cond(cond);
+                self.check_block(body);
+            },
+            Statement::Call(call) => {
+                if let ExprKind::Call(name, args) = &call.kind {
+                    self.check_call(call, name, args);
+                }
+            },
+            Statement::Output(args) | Statement::Input(args) => {
+                for e in args {
+                    self.type_of(e);
+                }
+            }
+        }
+    }
+
+    fn check_cond(&mut self, cond: &Expression) {
+        if let Some(t) = self.type_of(cond) {
+            if t.type_name() != TypeName::Bool {
+                self.diags.push(Diagnostic::error(Code::Condition, cond.span, &[&t.type_name().name()]));
+            }
+        }
+    }
+
+    /// Checks the arguments of a call, and returns the result type if
+    /// it's a function.
+    fn check_call(&mut self, call: &Expression, name: &str, args: &[Expression]) -> Option<TypeName> {
+        let types: Vec<_> = args.iter().map(|a| self.type_of(a)).collect();
+
+        let program = self.program;
+        let sub = match program.subprogram(name) {
+            Some(sub) => sub,
+            None => {
+                self.diags.push(Diagnostic::error(Code::UnknownSubprogram, call.span, &[name]));
+                return None;
+            }
+        };
+
+        if sub.params.len() != args.len() {
+            let d = Diagnostic::error(Code::Arity, call.span, &[name, &sub.params.len().to_string(), &args.len().to_string()])
+                .with_secondary(sub.span, Phrase::DefinedHere);
+            self.diags.push(d);
+        }
+        for ((p, a), t) in sub.params.iter().zip(args).zip(types) {
+            if p.mode != ParamMode::In && !a.is_assignable() {
+                let d = Diagnostic::error(Code::NotReference, a.span, &[&p.name])
+                    .with_secondary(p.span, Phrase::ParamDeclaredHere);
+                self.diags.push(d);
+                continue;
+            }
+            let t = match t {
+                Some(t) => t,
+                None => continue
+            };
+            // a `KI` parameter's value is copied back, a `CÍM` parameter
+            // is the argument itself, so it must be of the same type
+            let fits = match p.mode {
+                ParamMode::In => p.ptype.init_val().can_copy(t.as_ref()),
+                ParamMode::Out => t.can_copy(p.ptype.init_val().as_ref()),
+                ParamMode::Ref => t.type_name() == p.ptype
+            };
+            if !fits {
+                let d = Diagnostic::error(Code::Mismatch, a.span, &[&p.ptype.name(), &t.type_name().name()])
+                    .with_secondary(p.span, Phrase::ParamDeclaredHere);
+                self.diags.push(d);
+            }
+        }
+
+        sub.result.clone()
+    }
+
+    /// Prototype value of the expression's type, `None` if it has an
+    /// error (already reported).
+    fn type_of(&mut self, e: &Expression) -> Option<Box<dyn Type>> {
+        match &e.kind {
+            ExprKind::Literal(lit) => Some(lit.value()),
+            ExprKind::Variable(name) => match self.scope.get(name) {
+                Some(t) => Some(t.init_val()),
+                None => {
+                    self.diags.push(Diagnostic::error(Code::Undeclared, e.span, &[name]));
+                    None
+                }
+            },
+            ExprKind::Unary(op, a) => {
+                let a = self.type_of(a)?;
+                let t = a.unary_optype(*op);
+                if t.is_none() {
+                    self.diags.push(Diagnostic::error(Code::UnaryOperator, e.span, &[op.op(), &a.type_name().name()]));
+                }
+                t
+            },
+            ExprKind::Binary(op, a, b) => {
+                let (a, b) = (self.type_of(a), self.type_of(b));
+                let (a, b) = (a?, b?);
+                let t = a.binary_optype(*op, b.as_ref());
+                if t.is_none() {
+                    let name = match op {
+                        BinaryOperator::Bracket => "[ ]",
+                        _ => op.op()
+                    };
+                    let args = [name, &a.type_name().name(), &b.type_name().name()];
+                    self.diags.push(Diagnostic::error(Code::BinaryOperator, e.span, &args));
+                }
+                t
+            },
+            ExprKind::Call(name, args) => {
+                let sub = self.program.subprogram(name);
+                let result = self.check_call(e, name, args);
+                if sub.is_some() && result.is_none() {
+                    self.diags.push(Diagnostic::error(Code::NotFunction, e.span, &[name]));
+                }
+                result.map(|t| t.init_val())
+            }
+        }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::Parser;
+
+    fn codes(src: &str) -> Vec<Code> {
+        let program = Parser::from(src).parse().unwrap();
+        let mut diags = Diagnostics::new();
+        check(&program, &mut diags);
+        diags.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn well_typed() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 \x20 y: VALÓS\n\
+                 \x20 s: SZÖVEG\n\
+                 y := x / 2\n\
+                 s := s + 'a'\n\
+                 HA x > 0 ÉS NEM (y < 1) AKKOR\n\
+                 \x20 KI: negyzet(x)\n\
+                 HA_VÉGE\n\
+                 PROGRAM_VÉGE\n\
+                 FÜGGVÉNY negyzet(n: VALÓS) : VALÓS\n\
+                 \x20 negyzet := n * n\n\
+                 FÜGGVÉNY_VÉGE";
+        assert_eq!(codes(s), vec![]);
+    }
+
+    /// Negative powers are left to the runtime, see `types::tests::powers`.
+    #[test]
+    fn integer_powers() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 x := 2 ^ -1\n\
+                 x := 0 ^ -1\n\
+                 x := 2 ^ 0.5\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(codes(s), vec![Code::Mismatch]);
+    }
+
+    #[test]
+    fn type_errors() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 \x20 b: LOGIKAI\n\
+                 x := 1.5\n\
+                 b := x + b\n\
+                 CIKLUS AMÍG x\n\
+                 \x20 y := NAGY x\n\
+                 CIKLUS_VÉGE\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(codes(s), vec![
+            Code::Mismatch,
+            Code::BinaryOperator,
+            Code::Condition,
+            Code::Undeclared,
+            Code::UnaryOperator,
+        ]);
+    }
+
+    #[test]
+    fn calls() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 kiir(1, 2)\n\
+                 kiir(\"a\")\n\
+                 x := kiir(1)\n\
+                 torol()\n\
+                 PROGRAM_VÉGE\n\
+                 ELJÁRÁS kiir(a: EGÉSZ)\n\
+                 \x20 KI: a, x\n\
+                 ELJÁRÁS_VÉGE";
+        assert_eq!(codes(s), vec![
+            Code::Arity,
+            Code::Mismatch,
+            Code::NotFunction,
+            Code::UnknownSubprogram,
+            Code::Undeclared,
+        ]);
+    }
+
+    #[test]
+    fn param_modes() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 i: EGÉSZ\n\
+                 \x20 v: VALÓS\n\
+                 \x20 s: SZÖVEG\n\
+                 f(i, i, v)\n\
+                 f(1, i + 1, v)\n\
+                 f(v, v, s)\n\
+                 PROGRAM_VÉGE\n\
+                 ELJÁRÁS f(a: VALÓS, CÍM b: EGÉSZ, KI c: EGÉSZ)\n\
+                 \x20 b := b + 1\n\
+                 \x20 c := b\n\
+                 ELJÁRÁS_VÉGE";
+        assert_eq!(codes(s), vec![
+            Code::NotReference,
+            Code::Mismatch,
+            Code::Mismatch,
+        ]);
+    }
+
+    #[test]
+    fn arrays() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 t, u: TÖMB[5: EGÉSZ]\n\
+                 \x20 v: TÖMB[5: VALÓS]\n\
+                 t[|t|] := t[1] + 2\n\
+                 u := t\n\
+                 BE: t[2]\n\
+                 v := t\n\
+                 t[1] := 0.5\n\
+                 KI: t[IGAZ], -t\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(codes(s), vec![
+            Code::Mismatch,
+            Code::Mismatch,
+            Code::BinaryOperator,
+            Code::UnaryOperator,
+        ]);
+    }
+}
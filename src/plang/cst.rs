@@ -0,0 +1,396 @@
+use std::fmt;
+use super::lexer::{ Lexer, LexerToken };
+use super::tokenizer::{ Span, TokenType };
+use super::variable::TypeName;
+
+/// Text between tokens that doesn't take part in the syntax.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trivia<'a> {
+    Whitespace(&'a str),
+    /// A comment with its `**`.
+    Comment(&'a str)
+}
+
+impl<'a> Trivia<'a> {
+    pub fn text(&self) -> &'a str {
+        match self {
+            Trivia::Whitespace(s) | Trivia::Comment(s) => s
+        }
+    }
+}
+
+/// A token with the whitespace and comments before it. Line breaks are
+/// tokens, since they end statements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken<'a> {
+    pub ttype: TokenType,
+    pub text: &'a str,
+    /// Keywords in lowercase, without accents, empty for anything else.
+    pub lexical: String,
+    pub span: Span,
+    pub leading: Vec<Trivia<'a>>
+}
+
+impl fmt::Display for SyntaxToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for t in &self.leading {
+            f.write_str(t.text())?;
+        }
+        f.write_str(self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Program,
+    /// `ELJÁRÁS` or `FÜGGVÉNY`
+    Subprogram,
+    /// `VÁLTOZÓK:` and the declarations under it
+    Decls,
+    If,
+    While,
+    /// Lines inside a compound statement or definition
+    Block,
+
+    // single lines
+    /// The line opening a compound, like `HA ... AKKOR`
+    Header,
+    /// `KÜLÖNBEN` or `KÜLÖNBEN HA ... AKKOR`
+    Else,
+    /// The line closing a compound, like `HA_VÉGE`
+    End,
+    Decl,
+    Statement,
+    /// A line of nothing but whitespace, maybe with a comment
+    Empty
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>)
+}
+
+/// Node of the concrete syntax tree. The tree keeps every byte of the
+/// source, so printing it gives back the text it was built from,
+/// comments and spacing included. Statements are single lines of
+/// tokens, their expressions are left to the parser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode<'a> {
+    pub kind: NodeKind,
+    pub children: Vec<Element<'a>>
+}
+
+impl<'a> SyntaxNode<'a> {
+    fn new(kind: NodeKind) -> SyntaxNode<'a> {
+        SyntaxNode { kind, children: vec![] }
+    }
+
+    fn push(&mut self, node: SyntaxNode<'a>) {
+        self.children.push(Element::Node(node));
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(|e| match e {
+            Element::Node(n) => Some(n),
+            Element::Token(_) => None
+        })
+    }
+
+    /// Every token under the node, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'n>(&'n self, out: &mut Vec<&'n SyntaxToken<'a>>) {
+        for e in &self.children {
+            match e {
+                Element::Node(n) => n.collect_tokens(out),
+                Element::Token(t) => out.push(t)
+            }
+        }
+    }
+
+    /// Source covered by the node, without the trivia before it.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        Some(tokens.first()?.span.to(tokens.last()?.span))
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.children {
+            match e {
+                Element::Node(n) => write!(f, "{}", n)?,
+                Element::Token(t) => write!(f, "{}", t)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the syntax tree of the whole text of the lexer. It never
+/// fails: lines that don't fit anywhere become statements, blocks left
+/// open end with the file.
+pub fn parse_cst(lex: Lexer<'_>) -> SyntaxNode<'_> {
+    let (lines, eof) = lines(lex);
+    let mut b = Builder { lines, pos: 0, open: vec![] };
+
+    let mut file = SyntaxNode::new(NodeKind::File);
+    while b.pos < b.lines.len() {
+        let node = b.item();
+        file.push(node);
+    }
+    file.children.push(Element::Token(eof));
+    file
+}
+
+type Line<'a> = Vec<SyntaxToken<'a>>;
+
+/// Splits the tokens into lines, each ending with its line break, and
+/// the end of file token carrying the trivia after the last line.
+fn lines(mut lex: Lexer<'_>) -> (Vec<Line<'_>>, SyntaxToken<'_>) {
+    let src = lex.text();
+    let mut lines = vec![];
+    let mut line = vec![];
+    let mut leading = vec![];
+    let mut prev_end = 0;
+
+    for LexerToken { tok, lex: lexical } in lex.by_ref() {
+        let text = &src[tok.span.begin..tok.span.end];
+        gap(&mut leading, src, prev_end, tok.span.begin);
+        prev_end = tok.span.end;
+
+        if tok.ttype == TokenType::Comment {
+            leading.push(Trivia::Comment(text));
+            continue;
+        }
+        let is_eol = tok.ttype == TokenType::LineBreak;
+        line.push(SyntaxToken {
+            ttype: tok.ttype, text, lexical, span: tok.span, leading: std::mem::take(&mut leading)
+        });
+        if is_eol {
+            lines.push(std::mem::take(&mut line));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    gap(&mut leading, src, prev_end, src.len());
+    let span = lex.span();
+    let eof = SyntaxToken { ttype: TokenType::EOF, text: "", lexical: String::new(), span, leading };
+    (lines, eof)
+}
+
+/// Whitespace between two tokens, if there's any.
+fn gap<'a>(leading: &mut Vec<Trivia<'a>>, src: &'a str, from: usize, to: usize) {
+    if to > from {
+        leading.push(Trivia::Whitespace(&src[from..to]));
+    }
+}
+
+/// What a line does to the structure, by its first token.
+#[derive(Debug, PartialEq)]
+enum LineKind {
+    Empty,
+    Open(NodeKind, &'static str),
+    Else,
+    Close,
+    Decls,
+    Decl,
+    Statement
+}
+
+fn classify(line: &[SyntaxToken]) -> LineKind {
+    let first = match line.first() {
+        Some(t) if t.ttype != TokenType::LineBreak => t,
+        _ => return LineKind::Empty
+    };
+    match first.lexical.as_str() {
+        "program" => LineKind::Open(NodeKind::Program, "program_vege"),
+        "eljaras" => LineKind::Open(NodeKind::Subprogram, "eljaras_vege"),
+        "fuggveny" => LineKind::Open(NodeKind::Subprogram, "fuggveny_vege"),
+        "ha" => LineKind::Open(NodeKind::If, "ha_vege"),
+        "ciklus" => LineKind::Open(NodeKind::While, "ciklus_vege"),
+        "kulonben" => LineKind::Else,
+        "program_vege" | "eljaras_vege" | "fuggveny_vege" | "ha_vege" | "ciklus_vege" => LineKind::Close,
+        "valtozok" => LineKind::Decls,
+        _ if first.ttype == TokenType::Ident && is_decl_rest(line.get(1)) => LineKind::Decl,
+        _ => LineKind::Statement
+    }
+}
+
+/// Same test as the parser's: a name followed by `,`, `:` or a type.
+fn is_decl_rest(next: Option<&SyntaxToken>) -> bool {
+    match next {
+        Some(t) if t.ttype == TokenType::Operator => t.text == "," || t.text == ":",
+        Some(t) if t.ttype == TokenType::Keyword => TypeName::is_type_word(&t.lexical),
+        _ => false
+    }
+}
+
+struct Builder<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    /// Closing keywords of the compounds being built, innermost last.
+    open: Vec<&'static str>
+}
+
+impl<'a> Builder<'a> {
+    fn kind(&self) -> Option<LineKind> {
+        self.lines.get(self.pos).map(|l| classify(l))
+    }
+
+    fn closing_keyword(&self) -> &str {
+        self.lines[self.pos].first().map_or("", |t| t.lexical.as_str())
+    }
+
+    fn line(&mut self, kind: NodeKind) -> SyntaxNode<'a> {
+        let tokens = std::mem::take(&mut self.lines[self.pos]);
+        self.pos += 1;
+        SyntaxNode { kind, children: tokens.into_iter().map(Element::Token).collect() }
+    }
+
+    /// A line at the top level or inside a block.
+    fn item(&mut self) -> SyntaxNode<'a> {
+        match self.kind() {
+            Some(LineKind::Empty) => self.line(NodeKind::Empty),
+            Some(LineKind::Open(kind, end)) => self.compound(kind, end),
+            Some(LineKind::Decls) => self.decls(),
+            _ => self.line(NodeKind::Statement)
+        }
+    }
+
+    fn compound(&mut self, kind: NodeKind, end: &'static str) -> SyntaxNode<'a> {
+        let mut node = SyntaxNode::new(kind);
+        node.push(self.line(NodeKind::Header));
+        self.open.push(end);
+
+        loop {
+            let block = self.block(kind == NodeKind::If);
+            node.push(block);
+            match self.kind() {
+                Some(LineKind::Else) if kind == NodeKind::If => node.push(self.line(NodeKind::Else)),
+                Some(LineKind::Close) if self.closing_keyword() == end => {
+                    node.push(self.line(NodeKind::End));
+                    break;
+                },
+                // closes an outer block, or the file ended
+                _ => break
+            }
+        }
+        self.open.pop();
+        node
+    }
+
+    /// Lines up to one that ends or continues an open compound.
+    fn block(&mut self, in_if: bool) -> SyntaxNode<'a> {
+        let mut block = SyntaxNode::new(NodeKind::Block);
+        loop {
+            match self.kind() {
+                None => break,
+                Some(LineKind::Else) if in_if => break,
+                Some(LineKind::Close) if self.open.contains(&self.closing_keyword()) => break,
+                _ => {
+                    let node = self.item();
+                    block.push(node);
+                }
+            }
+        }
+        block
+    }
+
+    /// `VÁLTOZÓK:` and the declarations after it. Empty lines belong to
+    /// it only if there's another declaration after them.
+    fn decls(&mut self) -> SyntaxNode<'a> {
+        let mut node = SyntaxNode::new(NodeKind::Decls);
+        node.push(self.line(NodeKind::Header));
+        loop {
+            let mut ahead = self.pos;
+            while self.lines.get(ahead).map(|l| classify(l)) == Some(LineKind::Empty) {
+                ahead += 1;
+            }
+            if self.lines.get(ahead).map(|l| classify(l)) != Some(LineKind::Decl) {
+                break;
+            }
+            while self.pos < ahead {
+                node.push(self.line(NodeKind::Empty));
+            }
+            node.push(self.line(NodeKind::Decl));
+        }
+        node
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "** összeadás\r\n\
+                       PROGRAM p  \n\
+                       \x20 VÁLTOZÓK:\n\
+                       \x20   a, b: EGÉSZ ** két szám\n\
+                       \n\
+                       \x20   s: SZÖVEG\n\
+                       \n\
+                       \x20 HA a < b AKKOR\n\
+                       \t  KI: \"a\"\n\
+                       \x20 KÜLÖNBEN\n\
+                       \x20   CIKLUS AMÍG a>b\n\
+                       \x20     a := a - 1\n\
+                       \x20   CIKLUS_VÉGE\n\
+                       \x20 HA_VÉGE\n\
+                       PROGRAM_VÉGE\n\
+                       ** vége  ";
+
+    fn kinds(node: &SyntaxNode) -> Vec<NodeKind> {
+        node.nodes().map(|n| n.kind).collect()
+    }
+
+    #[test]
+    fn lossless() {
+        for src in [SRC, "", "\n", "  ", "x := 1", "HA x\n  ** open\n", "CIKLUS_VÉGE\n'\n$ \"a", "a\r\rb\r\n", "x (* a\nb *) y\n", "(* open\n"] {
+            assert_eq!(parse_cst(Lexer::from(src)).to_string(), src);
+        }
+    }
+
+    #[test]
+    fn structure() {
+        let file = parse_cst(Lexer::from(SRC));
+        assert_eq!(kinds(&file), [NodeKind::Empty, NodeKind::Program]);
+
+        let program = file.nodes().nth(1).unwrap();
+        assert_eq!(kinds(program), [NodeKind::Header, NodeKind::Block, NodeKind::End]);
+
+        let block = program.nodes().nth(1).unwrap();
+        assert_eq!(kinds(block), [NodeKind::Decls, NodeKind::Empty, NodeKind::If]);
+        let decls = block.nodes().next().unwrap();
+        assert_eq!(kinds(decls), [NodeKind::Header, NodeKind::Decl, NodeKind::Empty, NodeKind::Decl]);
+
+        let branch = block.nodes().nth(2).unwrap();
+        assert_eq!(kinds(branch), [NodeKind::Header, NodeKind::Block, NodeKind::Else, NodeKind::Block, NodeKind::End]);
+        assert_eq!(branch.nodes().nth(3).unwrap().to_string(),
+            "    CIKLUS AMÍG a>b\n      a := a - 1\n    CIKLUS_VÉGE\n");
+
+        // comments stay on their line
+        let decl = decls.nodes().nth(1).unwrap();
+        assert_eq!(decl.tokens().last().unwrap().leading, [Trivia::Whitespace(" "), Trivia::Comment("** két szám")]);
+    }
+
+    #[test]
+    fn unclosed_blocks() {
+        let file = parse_cst(Lexer::from("PROGRAM p\nHA x AKKOR\nCIKLUS AMÍG y\nPROGRAM_VÉGE\nHA_VÉGE\n"));
+        assert_eq!(kinds(&file), [NodeKind::Program, NodeKind::Statement]);
+
+        let program = file.nodes().next().unwrap();
+        assert_eq!(kinds(program), [NodeKind::Header, NodeKind::Block, NodeKind::End]);
+        let branch = program.nodes().nth(1).unwrap().nodes().next().unwrap();
+        assert_eq!(kinds(branch), [NodeKind::Header, NodeKind::Block]);
+    }
+}
@@ -1,6 +1,61 @@
-
 use super::variable::*;
+use super::tokenizer::Span;
+use super::diagnostic::{ Diagnostic, Code };
+use super::catalog::Phrase;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Declarations {
+    pub variables: Vec<VarDecl>
+}
+
+impl Declarations {
+    pub fn new() -> Declarations {
+        Declarations { variables: vec![] }
+    }
+
+    /// Adds a declaration to the block. If one of its names is already 
+    /// declared, the declaration is still added, and the error points at
+    /// both places.
+    pub fn push(&mut self, mut decl: VarDecl) -> Result<(), Diagnostic> {
+        let mut result = Ok(());
+        for (i, name) in decl.names.iter().enumerate() {
+            let prev = self.span_of(name)
+                .or_else(|| decl.names[..i].iter().position(|n| n == name).map(|j| decl.spans[j]));
+            if let Some(prev) = prev {
+                result = Err(Diagnostic::error(Code::DupName, decl.spans[i], &[name])
+                    .with_label(Phrase::DeclaredAgain)
+                    .with_secondary(prev, Phrase::FirstDeclaredHere));
+                break;
+            }
+        }
+
+        if let Some(last) = self.variables.last_mut() {
+            last.is_last = false;
+        }
+        decl.is_last = true;
+        self.variables.push(decl);
+        result
+    }
+
+    /// Where `name` is declared.
+    pub fn span_of(&self, name: &str) -> Option<Span> {
+        self.variables.iter()
+            .flat_map(|d| d.names.iter().zip(d.spans.iter()))
+            .find(|(n, _)| *n == name)
+            .map(|(_, s)| *s)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&VarDecl> {
+        self.variables.iter().find(|d| d.names.iter().any(|n| n == name))
+    }
+
+    pub fn type_of(&self, name: &str) -> Option<TypeName> {
+        self.find(name).map(|d| d.vtype.clone())
+    }
 
-pub struct Declarations<'a> {
-    variables: Vec<&'a VarDecl>
-}
\ No newline at end of file
+    /// Every declared name together with its type, in declaration order.
+    pub fn names(&self) -> impl Iterator<Item = (&String, &TypeName)> {
+        self.variables.iter()
+            .flat_map(|d| d.names.iter().map(move |n| (n, &d.vtype)))
+    }
+}
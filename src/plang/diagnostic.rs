@@ -0,0 +1,449 @@
+use std::fmt;
+use super::tokenizer::Span;
+use super::unicode::is_combining_mark;
+use super::catalog::{ Lang, Phrase, format_template, note_name };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+/// Stable identifier of every kind of problem we report. The codes
+/// never change meaning, so they can be searched for in logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    // tokenizer
+    UnterminatedLiteral,
+    InvalidChar,
+    InvalidNumber,
+    InvalidWord,
+    InvalidEscape,
+    UnterminatedComment,
+
+    // lexer
+    UnknownOperator,
+    MixedForms,
+
+    // parser
+    Expected,
+    DeclName,
+    DeclColon,
+    DeclType,
+    DupName,
+    NotAssignable,
+    MissingProgram,
+    DupProgram,
+    DupSubprogram,
+    Unexpected,
+    ArraySize,
+
+    // type checker
+    Undeclared,
+    Mismatch,
+    UnaryOperator,
+    BinaryOperator,
+    Condition,
+    UnknownSubprogram,
+    Arity,
+    NotFunction,
+    NotReference,
+
+    // runtime
+    Operator,
+    DivByZero,
+    Overflow,
+    Domain,
+    Input,
+    Io,
+    Unsupported,
+    Index,
+    Recursion
+}
+
+impl Code {
+    pub const ALL: [Code; 37] = [
+        Code::UnterminatedLiteral, Code::InvalidChar, Code::InvalidNumber, Code::InvalidWord,
+        Code::InvalidEscape, Code::UnterminatedComment,
+        Code::UnknownOperator, Code::MixedForms,
+        Code::Expected, Code::DeclName, Code::DeclColon, Code::DeclType, Code::DupName,
+        Code::NotAssignable, Code::MissingProgram, Code::DupProgram, Code::DupSubprogram,
+        Code::Unexpected, Code::ArraySize,
+        Code::Undeclared, Code::Mismatch, Code::UnaryOperator, Code::BinaryOperator,
+        Code::Condition, Code::UnknownSubprogram, Code::Arity, Code::NotFunction,
+        Code::NotReference,
+        Code::Operator, Code::DivByZero, Code::Overflow, Code::Domain, Code::Input,
+        Code::Io, Code::Unsupported, Code::Index, Code::Recursion
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Code::UnterminatedLiteral => "E0001",
+            Code::InvalidChar => "E0002",
+            Code::InvalidNumber => "E0003",
+            Code::InvalidWord => "E0004",
+            Code::InvalidEscape => "E0005",
+            Code::UnterminatedComment => "E0006",
+
+            Code::UnknownOperator => "E0010",
+            Code::MixedForms => "W0011",
+
+            Code::Expected => "E0100",
+            Code::DeclName => "E0101",
+            Code::DeclColon => "E0102",
+            Code::DeclType => "E0103",
+            Code::DupName => "E0104",
+            Code::NotAssignable => "E0105",
+            Code::MissingProgram => "E0106",
+            Code::DupProgram => "E0107",
+            Code::DupSubprogram => "E0108",
+            Code::Unexpected => "E0109",
+            Code::ArraySize => "E0110",
+
+            Code::Undeclared => "E0200",
+            Code::Mismatch => "E0201",
+            Code::UnaryOperator => "E0202",
+            Code::BinaryOperator => "E0203",
+            Code::Condition => "E0204",
+            Code::UnknownSubprogram => "E0205",
+            Code::Arity => "E0206",
+            Code::NotFunction => "E0207",
+            Code::NotReference => "E0208",
+
+            Code::Operator => "E0300",
+            Code::DivByZero => "E0301",
+            Code::Overflow => "E0302",
+            Code::Domain => "E0303",
+            Code::Input => "E0304",
+            Code::Io => "E0305",
+            Code::Unsupported => "E0306",
+            Code::Index => "E0307",
+            Code::Recursion => "E0308"
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Code> {
+        Code::ALL.iter().copied().find(|c| c.as_str() == code)
+    }
+}
+
+/// Argument of a message: either text shown as is, like a name from the
+/// source, or a phrase to translate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Text(String),
+    Phrase(Phrase)
+}
+
+impl Arg {
+    pub fn render(&self, lang: Lang) -> String {
+        match self {
+            Arg::Text(s) => s.clone(),
+            Arg::Phrase(p) => p.template(lang).to_string()
+        }
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(s: &str) -> Arg {
+        Arg::Text(s.to_string())
+    }
+}
+
+impl From<String> for Arg {
+    fn from(s: String) -> Arg {
+        Arg::Text(s)
+    }
+}
+
+impl From<Phrase> for Arg {
+    fn from(p: Phrase) -> Arg {
+        Arg::Phrase(p)
+    }
+}
+
+fn render_args(args: &[Arg], lang: Lang) -> Vec<String> {
+    args.iter().map(|a| a.render(lang)).collect()
+}
+
+/// Text of a label or note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub phrase: Phrase,
+    pub args: Vec<Arg>
+}
+
+impl Message {
+    pub fn new<A: Into<Arg>>(phrase: Phrase, arg: A) -> Message {
+        Message { phrase, args: vec![arg.into()] }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        format_template(self.phrase.template(lang), &render_args(&self.args, lang))
+    }
+}
+
+impl From<Phrase> for Message {
+    fn from(phrase: Phrase) -> Message {
+        Message { phrase, args: vec![] }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: Option<Message>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub args: Vec<Arg>,
+    pub primary: Label,
+    /// Boxed, as a diagnostic is the error of every parse step and most
+    /// of them have none of it.
+    pub context: Box<Context>
+}
+
+/// Other places a diagnostic points at, and notes after the source.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Context {
+    pub secondary: Vec<Label>,
+    pub notes: Vec<Message>
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, span: Span, args: &[&str]) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, span, args)
+    }
+
+    pub fn warning(code: Code, span: Span, args: &[&str]) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, span, args)
+    }
+
+    fn new(severity: Severity, code: Code, span: Span, args: &[&str]) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            args: args.iter().map(|&a| a.into()).collect(),
+            primary: Label { span, message: None },
+            context: Box::default()
+        }
+    }
+
+    /// Adds an argument after the ones given on creation.
+    pub fn with_arg<A: Into<Arg>>(mut self, arg: A) -> Diagnostic {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Text under the caret of the primary span.
+    pub fn with_label<M: Into<Message>>(mut self, message: M) -> Diagnostic {
+        self.primary.message = Some(message.into());
+        self
+    }
+
+    pub fn with_secondary<M: Into<Message>>(mut self, span: Span, message: M) -> Diagnostic {
+        self.context.secondary.push(Label { span, message: Some(message.into()) });
+        self
+    }
+
+    pub fn with_note<M: Into<Message>>(mut self, note: M) -> Diagnostic {
+        self.context.notes.push(note.into());
+        self
+    }
+
+    pub fn span(&self) -> Span {
+        self.primary.span
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        format_template(self.code.template(lang), &render_args(&self.args, lang))
+    }
+
+    /// Renders the diagnostic with the offending source lines:
+    ///
+    /// ```text
+    /// error[E0100]: expected `)`, found end of line
+    ///  --> prog.plang:2:8
+    ///   |
+    /// 2 | x := (1
+    ///   |        ^ expected `)`
+    /// ```
+    pub fn render(&self, src: &str, path: &str, lang: Lang) -> String {
+        let mut labels = vec![(&self.primary, '^')];
+        labels.extend(self.context.secondary.iter().map(|l| (l, '-')));
+
+        let width = labels.iter()
+            .map(|(l, _)| l.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        let mut s = format!("{}[{}]: {}\n", self.severity.name(lang), self.code.as_str(), self.message(lang));
+        s += &format!("{}--> {}:{}\n", gutter, path, self.primary.span);
+        s += &format!("{} |\n", gutter);
+
+        for (label, mark) in labels {
+            let (line, prefix, len) = source_line(src, label.span);
+            s += &format!("{:>w$} | {}\n", label.span.line, line, w = width);
+            s += &format!("{} | {}{}", gutter, prefix, mark.to_string().repeat(len));
+            if let Some(message) = &label.message {
+                s += " ";
+                s += &message.render(lang);
+            }
+            s += "\n";
+        }
+
+        for note in &self.context.notes {
+            s += &format!("{} = {}: {}\n", gutter, note_name(lang), note.render(lang));
+        }
+        s
+    }
+}
+
+/// The line a span starts on, the whitespace to put before its underline
+/// (keeping tabs so it lines up) and the underline's length in characters.
+fn source_line(src: &str, span: Span) -> (&str, String, usize) {
+    let begin = span.begin.min(src.len());
+    let line_begin = src[..begin].rfind(&['\r', '\n'][..]).map_or(0, |i| i + 1);
+    let line_end = src[begin..].find(&['\r', '\n'][..]).map_or(src.len(), |i| begin + i);
+
+    // combining marks take no column of their own
+    let prefix = src[line_begin..begin].chars()
+        .filter(|&c| !is_combining_mark(c))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end = span.end.clamp(begin, line_end);
+    let width = src[begin..end].chars().filter(|&c| !is_combining_mark(c)).count().max(1);
+
+    (&src[line_begin..line_end], prefix, width)
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lang = Lang::default();
+        write!(f, "{}: {}[{}]: {}", self.primary.span, self.severity.name(lang), self.code.as_str(), self.message(lang))
+    }
+}
+
+/// Everything reported about one source file, kept in source order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { items: vec![] }
+    }
+
+    pub fn push(&mut self, d: Diagnostic) {
+        let at = self.items.iter()
+            .position(|x| x.span().begin > d.span().begin)
+            .unwrap_or(self.items.len());
+        self.items.insert(at, d);
+    }
+
+    pub fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, it: I) {
+        for d in it {
+            self.push(d);
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.items.iter().filter(|d| d.severity == Severity::Error).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+
+    pub fn render(&self, src: &str, path: &str, lang: Lang) -> String {
+        self.items.iter()
+            .map(|d| d.render(src, path, lang))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(begin: usize, end: usize, line: usize, col: usize) -> Span {
+        Span { begin, end, line, col }
+    }
+
+    #[test]
+    fn render_primary() {
+        let src = "PROGRAM p\nx := (1\nPROGRAM_VÉGE";
+        let d = Diagnostic::error(Code::Expected, span(17, 18, 2, 8), &["`)`"])
+            .with_arg(Phrase::EndOfLine)
+            .with_label(Message::new(Phrase::ExpectedHere, "`)`"));
+
+        let expect = "\
+error[E0100]: expected `)`, found end of line
+ --> p.plang:2:8
+  |
+2 | x := (1
+  |        ^ expected `)`
+";
+        assert_eq!(d.render(src, "p.plang", Lang::En), expect);
+    }
+
+    #[test]
+    fn render_secondary() {
+        let src = "VÁLTOZÓK:\n\tx: EGÉSZ\n\ty, x: VALÓS\n";
+        let d = Diagnostic::error(Code::DupName, span(27, 28, 3, 5), &["x"])
+            .with_label(Phrase::DeclaredAgain)
+            .with_secondary(span(13, 14, 2, 2), Phrase::FirstDeclaredHere)
+            .with_note(Phrase::DeclSyntax);
+
+        let expect = "\
+hiba[E0104]: `x` már deklarálva van
+ --> p.plang:3:5
+  |
+3 | \ty, x: VALÓS
+  | \t   ^ újra deklarálva
+2 | \tx: EGÉSZ
+  | \t- először itt deklarálva
+  = megjegyzés: a változók deklarálása: `név, név: TÍPUS`
+";
+        assert_eq!(d.render(src, "p.plang", Lang::Hu), expect);
+    }
+
+    #[test]
+    fn source_order() {
+        let mut ds = Diagnostics::new();
+        ds.push(Diagnostic::warning(Code::Unexpected, span(10, 11, 2, 1), &["a"]));
+        ds.push(Diagnostic::error(Code::Unexpected, span(2, 3, 1, 3), &["b"]));
+
+        let args: Vec<String> = ds.iter().map(|d| d.args[0].render(Lang::En)).collect();
+        assert_eq!(args, vec!["b", "a"]);
+        assert_eq!(ds.error_count(), 1);
+    }
+}
@@ -0,0 +1,229 @@
+use super::binary_operator::BinaryOperator;
+use super::tokenizer::NumberPolicy;
+use super::unicode::nfc;
+
+/// Keywords of the default dialect as they are written. What they mean
+/// is their lowercase, unaccented form, which the parser looks for.
+const HUNGARIAN: [&str; 50] = [
+    "PROGRAM", "PROGRAM_VÉGE", "ELJÁRÁS", "ELJÁRÁS_VÉGE",
+    "FÜGGVÉNY", "FÜGGVÉNY_VÉGE", "VÁLTOZÓK", "HA", "AKKOR",
+    "HA_VÉGE", "KÜLÖNBEN", "CIKLUS", "AMÍG", "CIKLUS_VÉGE", "EGÉSZ",
+    "VALÓS", "SZÖVEG", "KARAKTER", "LOGIKAI", "IGAZ", "HAMIS",
+    "SV", "KI", "BE", "SIN", "COS", "TAN", "LOG", "EXP", "RND",
+    "ARCSIN", "ARCCOS", "ARCTAN", "KIS", "NAGY", "KEREK", "BETŰ",
+    "SZÁM", "NEM", "ÉS", "VAGY", "KIFÁJL", "BEFÁJL", "MEGNYIT",
+    "LEZÁR", "VÉGE", "DIV", "MOD", "TÖMB", "CÍM"
+];
+
+/// The same keywords in English, in the same order.
+const ENGLISH: [&str; 50] = [
+    "PROGRAM", "END_PROGRAM", "PROCEDURE", "END_PROCEDURE",
+    "FUNCTION", "END_FUNCTION", "VARIABLES", "IF", "THEN",
+    "END_IF", "ELSE", "LOOP", "WHILE", "END_LOOP", "INTEGER",
+    "REAL", "STRING", "CHARACTER", "BOOLEAN", "TRUE", "FALSE",
+    "NL", "OUT", "IN", "SIN", "COS", "TAN", "LOG", "EXP", "RND",
+    "ARCSIN", "ARCCOS", "ARCTAN", "LOWER", "UPPER", "ROUND", "LETTER",
+    "NUMBER", "NOT", "AND", "OR", "OUTFILE", "INFILE", "OPEN",
+    "CLOSE", "EOF", "DIV", "MOD", "ARRAY", "REF"
+];
+
+/// `DIV` and `MOD` are operators too, but they are words, so they're
+/// among the keywords.
+const OPERATORS: [&str; 20] = [
+    ",", ":", "+", "-", "*", "/", "^", "@", "(", ")", "[", "]", "|",
+    ":=", "=", "/=", "<", ">", "<=", ">="
+];
+
+/// Lowercase and without the Hungarian accents, the form keywords are
+/// compared in. Accents typed as combining marks are composed first.
+pub fn normalize(word: &str) -> String {
+    nfc(word).to_lowercase().chars().map(|c| match c {
+        'á' => 'a',
+        'í' => 'i',
+        'é' => 'e',
+        'ó'|'ö'|'ő' => 'o',
+        'ú'|'ü'|'ű' => 'u',
+        _ => c
+    }).collect()
+}
+
+/// A variant of PLang: the words and symbols the lexer recognizes and
+/// how literals are written. Keywords of other dialects are mapped to
+/// the meaning they have in the default one, so the parser doesn't
+/// need to know about dialects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialect {
+    /// Keywords as written, with their normalized spelling and meaning.
+    keywords: Vec<Keyword>,
+    /// Symbols as written, with the one of the default dialect they
+    /// stand for.
+    operators: Vec<(&'static str, String)>,
+    comments: Vec<(String, String)>,
+    pub numbers: NumberPolicy
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Keyword {
+    written: String,
+    normalized: String,
+    meaning: String
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::hungarian()
+    }
+}
+
+impl Dialect {
+    fn with_names(names: &[&str], numbers: NumberPolicy) -> Dialect {
+        let mut d = Dialect {
+            keywords: Vec::new(),
+            operators: OPERATORS.iter().map(|&op| (op, op.to_string())).collect(),
+            comments: vec![("**".into(), String::new()), ("(*".into(), "*)".into())],
+            numbers
+        };
+        for (name, meaning) in names.iter().zip(HUNGARIAN.iter()) {
+            d = d.with_keyword(name, &normalize(meaning));
+        }
+        d
+    }
+
+    /// The language as it's taught here, and the default.
+    pub fn hungarian() -> Dialect {
+        Dialect::with_names(&HUNGARIAN, NumberPolicy::Hungarian)
+    }
+
+    pub fn english() -> Dialect {
+        Dialect::with_names(&ENGLISH, NumberPolicy::Dot)
+    }
+
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name.to_lowercase().as_str() {
+            "hu" | "magyar" | "hungarian" => Some(Dialect::hungarian()),
+            "en" | "angol" | "english" => Some(Dialect::english()),
+            _ => None
+        }
+    }
+
+    /// Adds a keyword with the meaning of a default one, e.g.
+    /// `with_keyword("AMIKOR", "ha")`. A meaning can have any number of
+    /// spellings, the first one is used in messages.
+    pub fn with_keyword(self, written: &str, meaning: &str) -> Dialect {
+        let mut d = self.without_keyword(written);
+        d.keywords.push(Keyword {
+            written: written.to_string(),
+            normalized: normalize(written),
+            meaning: meaning.to_string()
+        });
+        d
+    }
+
+    /// Makes a keyword an ordinary name.
+    pub fn without_keyword(mut self, written: &str) -> Dialect {
+        let normalized = normalize(written);
+        self.keywords.retain(|k| k.normalized != normalized);
+        self
+    }
+
+    /// Adds a symbol standing for a binary operator, e.g.
+    /// `with_operator("<>", BinaryOperator::Ne)`, or gives an existing
+    /// symbol that meaning.
+    pub fn with_operator(mut self, written: &'static str, meaning: BinaryOperator) -> Dialect {
+        self.operators.retain(|(op, _)| *op != written);
+        self.operators.push((written, normalize(meaning.op())));
+        self
+    }
+
+    /// Adds a kind of comment from `open` to `close`, or to the end of
+    /// the line if `close` is empty.
+    pub fn with_comment(mut self, open: &str, close: &str) -> Dialect {
+        self.comments.push((open.to_string(), close.to_string()));
+        self
+    }
+
+    pub fn with_numbers(mut self, numbers: NumberPolicy) -> Dialect {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Meaning of a word if it's a keyword.
+    pub fn keyword(&self, word: &str) -> Option<&str> {
+        let normalized = normalize(word);
+        self.keywords.iter()
+            .find(|k| k.normalized == normalized)
+            .map(|k| k.meaning.as_str())
+    }
+
+    /// How the keyword meaning `meaning` is written in this dialect.
+    pub fn name(&self, meaning: &str) -> String {
+        match self.keywords.iter().find(|k| k.meaning == meaning) {
+            Some(k) => k.written.clone(),
+            None => meaning.to_uppercase()
+        }
+    }
+
+    /// Keywords as they are compared, for the tokenizer to tell them
+    /// from names.
+    pub fn keywords(&self) -> Vec<String> {
+        self.keywords.iter().map(|k| k.normalized.clone()).collect()
+    }
+
+    /// Meaning of a symbol if it's an operator: how it's written in
+    /// the default dialect, or the meaning of the keyword it stands for.
+    pub fn operator(&self, s: &str) -> Option<&str> {
+        self.operators.iter()
+            .find(|(op, _)| *op == s)
+            .map(|(_, meaning)| meaning.as_str())
+    }
+
+    /// Symbol operators, the ones the tokenizer has to split runs of
+    /// punctuation into.
+    pub fn operators(&self) -> Vec<&'static str> {
+        self.operators.iter().map(|&(op, _)| op).collect()
+    }
+
+    pub fn comments(&self) -> &[(String, String)] {
+        &self.comments
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords() {
+        let hu = Dialect::hungarian();
+        assert_eq!(hu.keyword("Különben"), Some("kulonben"));
+        assert_eq!(hu.keyword("kulonben"), Some("kulonben"));
+        assert_eq!(hu.keyword("else"), None);
+        assert_eq!(hu.keyword("KU\u{308}LO\u{308}NBEN"), Some("kulonben"));
+        assert_eq!(hu.name("ha_vege"), "HA_VÉGE");
+
+        let en = Dialect::english();
+        assert_eq!(en.keyword("end_if"), Some("ha_vege"));
+        assert_eq!(en.keyword("ha"), None);
+        assert_eq!(en.name("ha_vege"), "END_IF");
+    }
+
+    #[test]
+    fn variants() {
+        let d = Dialect::hungarian()
+            .with_keyword("AMIKOR", "ha")
+            .without_keyword("HA")
+            .with_operator("<>", BinaryOperator::Ne)
+            .with_operator("&&", BinaryOperator::And)
+            .with_comment("{", "}");
+
+        assert_eq!(d.keyword("amikor"), Some("ha"));
+        assert_eq!(d.keyword("ha"), None);
+        assert_eq!(d.name("ha"), "AMIKOR");
+        assert_eq!(d.operator("<>"), Some("/="));
+        assert_eq!(d.operator("&&"), Some("es"));
+        assert_eq!(d.operator("+"), Some("+"));
+        assert_eq!(d.operator("!"), None);
+        assert_eq!(d.comments().len(), 3);
+    }
+}
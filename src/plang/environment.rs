@@ -1,4 +1,646 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::io::{ self, BufRead, Write };
+use super::ast::*;
+use super::declarations::Declarations;
+use super::variable::{ Type, TypeName };
+use super::binary_operator::BinaryOperator;
+use super::diagnostic::Code;
+use super::catalog::{ Lang, format_template };
 
-pub struct Environment {
-    
-}
\ No newline at end of file
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    /// Variable used without being declared
+    Undeclared(String),
+    /// Value of the second type where the first was expected
+    Mismatch(String, String),
+    /// Operator not applicable to a value of the given type
+    Operator(&'static str, String),
+    DivByZero,
+    Overflow,
+    /// Argument outside the domain of the function
+    Domain(&'static str),
+    /// `BE` read something that's not a value of the given type
+    Input(String, String),
+    Io(String),
+    Unsupported(String),
+    /// Index outside `1..=size` of an array of the given size
+    Index(i64, usize),
+    UnknownSubprogram(String),
+    /// Subprogram called with the wrong number of arguments: its name,
+    /// the number of parameters and of arguments
+    Arity(String, usize, usize),
+    /// Procedure called for its value
+    NotFunction(String),
+    /// Value passed to a `KI` or `CÍM` parameter
+    NotReference(String),
+    /// More than `MAX_DEPTH` calls in progress
+    Recursion
+}
+
+impl RuntimeError {
+    pub const fn code(&self) -> Code {
+        match self {
+            RuntimeError::Undeclared(_) => Code::Undeclared,
+            RuntimeError::Mismatch(_, _) => Code::Mismatch,
+            RuntimeError::Operator(_, _) => Code::Operator,
+            RuntimeError::DivByZero => Code::DivByZero,
+            RuntimeError::Overflow => Code::Overflow,
+            RuntimeError::Domain(_) => Code::Domain,
+            RuntimeError::Input(_, _) => Code::Input,
+            RuntimeError::Io(_) => Code::Io,
+            RuntimeError::Unsupported(_) => Code::Unsupported,
+            RuntimeError::Index(_, _) => Code::Index,
+            RuntimeError::UnknownSubprogram(_) => Code::UnknownSubprogram,
+            RuntimeError::Arity(_, _, _) => Code::Arity,
+            RuntimeError::NotFunction(_) => Code::NotFunction,
+            RuntimeError::NotReference(_) => Code::NotReference,
+            RuntimeError::Recursion => Code::Recursion
+        }
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        let args = match self {
+            RuntimeError::Undeclared(name) | RuntimeError::UnknownSubprogram(name) |
+            RuntimeError::NotFunction(name) | RuntimeError::NotReference(name) => vec![name.clone()],
+            RuntimeError::Mismatch(a, b) => vec![a.clone(), b.clone()],
+            RuntimeError::Operator(op, t) => vec![op.to_string(), t.clone()],
+            RuntimeError::DivByZero | RuntimeError::Overflow => vec![],
+            RuntimeError::Domain(op) => vec![op.to_string()],
+            RuntimeError::Input(s, t) => vec![s.clone(), t.clone()],
+            RuntimeError::Io(msg) | RuntimeError::Unsupported(msg) => vec![msg.clone()],
+            RuntimeError::Index(i, size) => vec![i.to_string(), size.to_string()],
+            RuntimeError::Arity(name, params, args) => vec![name.clone(), params.to_string(), args.to_string()],
+            RuntimeError::Recursion => vec![MAX_DEPTH.to_string()]
+        };
+        format_template(self.code().template(lang), &args)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]: {}", self.code().as_str(), self.message(Lang::default()))
+    }
+}
+
+impl From<io::Error> for RuntimeError {
+    fn from(err: io::Error) -> Self {
+        RuntimeError::Io(err.to_string())
+    }
+}
+
+pub type RunResult<T> = Result<T, RuntimeError>;
+
+/// Calls that can be in progress at once, so that endless recursion
+/// stops with an error instead of overflowing the stack of the thread.
+pub const MAX_DEPTH: usize = 500;
+
+/// Stack for the thread running a program, enough for `MAX_DEPTH` calls
+/// in a debug build. The main thread may have as little as 1 MiB.
+pub const STACK_SIZE: usize = 64 << 20;
+
+/// Variables of the main program or of a subprogram being run.
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Box<dyn Type>>,
+    /// `CÍM` parameters, which stand for the argument they got
+    refs: HashMap<String, Rc<Location>>
+}
+
+/// A variable or an element of one, in the frame of the main program if
+/// `frame` is `None`, in `frames[i]` otherwise. Indices are evaluated
+/// when the location is taken, so they don't change with the variables
+/// they were computed from.
+struct Location {
+    frame: Option<usize>,
+    name: String,
+    indices: Vec<Box<dyn Type>>
+}
+
+/// Runtime state of a program: variable bindings and the streams
+/// `BE` and `KI` talk to.
+pub struct Environment<'a> {
+    /// Variables of the main program
+    main: Frame,
+    /// Parameters and locals of the subprograms being run, innermost
+    /// last. A subprogram only sees its own frame.
+    frames: Vec<Frame>,
+    subprograms: Rc<[Subprogram]>,
+    input: Box<dyn BufRead + 'a>,
+    output: Box<dyn Write + 'a>
+}
+
+impl<'a> Environment<'a> {
+    /// Environment reading standard input and writing standard output.
+    pub fn new() -> Environment<'a> {
+        let stdin = io::BufReader::new(io::stdin());
+        Environment::with_io(Box::new(stdin), Box::new(io::stdout()))
+    }
+
+    pub fn with_io(input: Box<dyn BufRead + 'a>, output: Box<dyn Write + 'a>) -> Environment<'a> {
+        Environment { main: Frame::default(), frames: vec![], subprograms: Rc::from(vec![]), input, output }
+    }
+
+    /// Binds every declared name to the initial value of its type.
+    pub fn declare(&mut self, decls: &Declarations) {
+        for (name, vtype) in decls.names() {
+            self.main.vars.insert(name.clone(), vtype.init_val());
+        }
+    }
+
+    /// Variables visible in the code being run.
+    fn scope(&self) -> &Frame {
+        self.frames.last().unwrap_or(&self.main)
+    }
+
+    fn scope_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap_or(&mut self.main)
+    }
+
+    /// Value of a variable, or of the argument if it's a `CÍM`
+    /// parameter.
+    pub fn get(&mut self, name: &str) -> RunResult<&dyn Type> {
+        self.variable(name).map(|v| &**v)
+    }
+
+    /// Assigns to a declared variable, converting the value to the
+    /// variable's type.
+    pub fn set(&mut self, name: &str, value: &dyn Type) -> RunResult<()> {
+        store(self.variable(name)?, value)
+    }
+
+    /// The variable `name` stands for in the code being run.
+    fn variable(&mut self, name: &str) -> RunResult<&mut Box<dyn Type>> {
+        match self.scope().refs.get(name).cloned() {
+            Some(loc) => self.at(&loc),
+            None => self.scope_mut().vars.get_mut(name)
+                .ok_or_else(|| RuntimeError::Undeclared(name.to_string()))
+        }
+    }
+
+    /// The variable or element at `loc`.
+    fn at(&mut self, loc: &Location) -> RunResult<&mut Box<dyn Type>> {
+        let frame = match loc.frame {
+            Some(i) => &mut self.frames[i],
+            None => &mut self.main
+        };
+        let mut slot = frame.vars.get_mut(&loc.name)
+            .ok_or_else(|| RuntimeError::Undeclared(loc.name.clone()))?;
+        for x in &loc.indices {
+            slot = slot.access_mut(BinaryOperator::Bracket, x.as_ref())?;
+        }
+        Ok(slot)
+    }
+
+    /// Where an assignable expression is now, following `CÍM`
+    /// parameters to their arguments.
+    fn locate(&mut self, target: &Expression) -> RunResult<Location> {
+        match &target.kind {
+            ExprKind::Variable(name) => {
+                if let Some(loc) = self.scope().refs.get(name) {
+                    let indices = loc.indices.iter().map(|x| x.copy(x.as_ref())).collect();
+                    return Ok(Location { frame: loc.frame, name: loc.name.clone(), indices });
+                }
+                if !self.scope().vars.contains_key(name) {
+                    return Err(RuntimeError::Undeclared(name.to_string()));
+                }
+                Ok(Location { frame: self.frames.len().checked_sub(1), name: name.clone(), indices: vec![] })
+            },
+            ExprKind::Binary(_, a, b) if target.is_assignable() => {
+                let x = self.eval(b)?;
+                let mut loc = self.locate(a)?;
+                loc.indices.push(x);
+                Ok(loc)
+            },
+            _ => Err(RuntimeError::Unsupported(String::from("assignment to an expression")))
+        }
+    }
+
+    pub fn run(&mut self, program: &Program) -> RunResult<()> {
+        self.subprograms = Rc::from(program.subprograms.clone());
+        self.declare(&program.decls);
+        self.exec_block(&program.body)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    pub fn exec_block(&mut self, block: &[Statement]) -> RunResult<()> {
+        for s in block {
+            self.exec(s)?;
+        }
+        Ok(())
+    }
+
+    pub fn exec(&mut self, stmt: &Statement) -> RunResult<()> {
+        match stmt {
+            Statement::Assign(target, value) => {
+                let v = self.eval(value)?;
+                self.assign(target, v.as_ref())
+            },
+            Statement::If { branches, otherwise } => {
+                for (cond, body) in branches {
+                    if self.eval_cond(cond)? {
+                        return self.exec_block(body);
+                    }
+                }
+                match otherwise {
+                    Some(body) => self.exec_block(body),
+                    None => Ok(())
+                }
+            },
+            Statement::While(cond, body) => {
+                while self.eval_cond(cond)? {
+                    self.exec_block(body)?;
+                }
+                Ok(())
+            },
+            Statement::Call(call) => match &call.kind {
+                ExprKind::Call(name, args) => self.call(name, args).map(|_| ()),
+                _ => self.eval(call).map(|_| ())
+            },
+            Statement::Output(args) => {
+                let mut s = String::new();
+                for e in args {
+                    self.eval(e)?.print_data(&mut s);
+                }
+                writeln!(self.output, "{}", s)?;
+                Ok(())
+            },
+            Statement::Input(targets) => {
+                self.output.flush()?;
+                for target in targets {
+                    let mut line = String::new();
+                    self.input.read_line(&mut line)?;
+                    let line = line.trim_end_matches(&['\r', '\n'][..]);
+
+                    let proto = self.eval(target)?;
+                    let value = match proto.parse_data(line) {
+                        Some(v) => v,
+                        None => return Err(RuntimeError::Input(line.to_string(), proto.type_name().name()))
+                    };
+                    self.assign(target, value.as_ref())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn assign(&mut self, target: &Expression, value: &dyn Type) -> RunResult<()> {
+        let slot = self.slot(target)?;
+        store(slot, value)
+    }
+
+    /// The variable or array element an assignable expression stands
+    /// for. Indices are evaluated first, as that needs `self` too.
+    fn slot(&mut self, target: &Expression) -> RunResult<&mut Box<dyn Type>> {
+        match &target.kind {
+            ExprKind::Variable(name) => self.variable(name),
+            ExprKind::Binary(op, a, b) if target.is_assignable() => {
+                let x = self.eval(b)?;
+                self.slot(a)?.access_mut(*op, x.as_ref())
+            },
+            _ => Err(RuntimeError::Unsupported(String::from("assignment to an expression")))
+        }
+    }
+
+    fn eval_cond(&mut self, cond: &Expression) -> RunResult<bool> {
+        let v = self.eval(cond)?;
+        if v.type_name() != TypeName::Bool {
+            return Err(RuntimeError::Mismatch(TypeName::Bool.name(), v.type_name().name()));
+        }
+        Ok(v.const_nval() != 0.0)
+    }
+
+    pub fn eval(&mut self, e: &Expression) -> RunResult<Box<dyn Type>> {
+        match &e.kind {
+            ExprKind::Literal(lit) => Ok(lit.value()),
+            ExprKind::Variable(name) => self.get(name).map(|v| v.copy(v)),
+            ExprKind::Unary(op, a) => {
+                let a = self.eval(a)?;
+                a.unary_apply(*op)
+            },
+            // elements are read in place, without copying the array
+            ExprKind::Binary(op, a, b) if e.is_assignable() => {
+                let x = self.eval(b)?;
+                self.slot(a)?.access(*op, x.as_ref())
+            },
+            ExprKind::Binary(op, a, b) => {
+                let a = self.eval(a)?;
+
+                // ÉS and VAGY don't evaluate their right side if the left
+                // one already decides the result
+                if a.type_name() == TypeName::Bool {
+                    let short = match op {
+                        BinaryOperator::And => a.const_nval() == 0.0,
+                        BinaryOperator::Or => a.const_nval() != 0.0,
+                        _ => false
+                    };
+                    if short {
+                        return Ok(a);
+                    }
+                }
+
+                let b = self.eval(b)?;
+                a.binary_apply(*op, b.as_ref())
+            },
+            ExprKind::Call(name, args) => {
+                self.call(name, args)?.ok_or_else(|| RuntimeError::NotFunction(name.clone()))
+            }
+        }
+    }
+
+    /// Runs a subprogram in a frame of its own, returning the result if
+    /// it's a function. A `CÍM` parameter is the argument itself, a `KI`
+    /// one is copied to it when the subprogram returns. Either argument
+    /// is located before the call, so `f(i, t[i])` changes the element
+    /// `i` was the index of at the time.
+    fn call(&mut self, name: &str, args: &[Expression]) -> RunResult<Option<Box<dyn Type>>> {
+        let subprograms = Rc::clone(&self.subprograms);
+        let sub = match subprograms.iter().find(|s| s.name == name) {
+            Some(sub) => sub,
+            None => return Err(RuntimeError::UnknownSubprogram(name.to_string()))
+        };
+        if sub.params.len() != args.len() {
+            return Err(RuntimeError::Arity(name.to_string(), sub.params.len(), args.len()));
+        }
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(RuntimeError::Recursion);
+        }
+
+        // arguments are evaluated in the caller's frame
+        let mut frame = Frame::default();
+        let mut outs = Vec::new();
+        for (p, a) in sub.params.iter().zip(args) {
+            if p.mode == ParamMode::In {
+                let mut slot = p.ptype.init_val();
+                store(&mut slot, self.eval(a)?.as_ref())?;
+                frame.vars.insert(p.name.clone(), slot);
+                continue;
+            }
+            if !a.is_assignable() {
+                return Err(RuntimeError::NotReference(p.name.clone()));
+            }
+            let loc = self.locate(a)?;
+            let t = self.at(&loc)?.type_name();
+            if p.mode == ParamMode::Out {
+                frame.vars.insert(p.name.clone(), p.ptype.init_val());
+                outs.push((&p.name, loc));
+            } else if t == p.ptype {
+                frame.refs.insert(p.name.clone(), Rc::new(loc));
+            } else {
+                return Err(RuntimeError::Mismatch(p.ptype.name(), t.name()));
+            }
+        }
+        // inside a function its name is the variable holding the result
+        if let Some(t) = &sub.result {
+            frame.vars.insert(sub.name.clone(), t.init_val());
+        }
+        for (local, vtype) in sub.decls.names() {
+            frame.vars.insert(local.clone(), vtype.init_val());
+        }
+
+        self.frames.push(frame);
+        let done = self.exec_block(&sub.body);
+        let mut frame = self.frames.pop().unwrap_or_default();
+        done?;
+
+        for (name, loc) in outs {
+            if let Some(v) = frame.vars.remove(name) {
+                store(self.at(&loc)?, v.as_ref())?;
+            }
+        }
+        Ok(sub.result.as_ref().and_then(|_| frame.vars.remove(&sub.name)))
+    }
+}
+
+/// Assigns to a variable or array element, converting the value to
+/// its type.
+fn store(slot: &mut Box<dyn Type>, value: &dyn Type) -> RunResult<()> {
+    if !slot.can_copy(value) {
+        return Err(RuntimeError::Mismatch(slot.type_name().name(), value.type_name().name()));
+    }
+    *slot = slot.copy(value);
+    Ok(())
+}
+
+impl Default for Environment<'_> {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::Parser;
+
+    fn run(src: &str, input: &str) -> RunResult<String> {
+        let program = Parser::from(src).parse().unwrap();
+        let mut out: Vec<u8> = vec![];
+        {
+            let mut env = Environment::with_io(Box::new(input.as_bytes()), Box::new(&mut out));
+            env.run(&program)?;
+        }
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn sum_loop() {
+        let s = "PROGRAM osszeg\n\
+                 VÁLTOZÓK:\n\
+                 \x20 i, n, s: EGÉSZ\n\
+                 BE: n\n\
+                 i := 1\n\
+                 CIKLUS AMÍG i <= n\n\
+                 \x20 s := s + i\n\
+                 \x20 i := i + 1\n\
+                 CIKLUS_VÉGE\n\
+                 KI: \"osszeg: \", s\n\
+                 PROGRAM_VÉGE\n";
+        assert_eq!(run(s, "10\n").unwrap(), "osszeg: 55\n");
+    }
+
+    #[test]
+    fn branches() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 BE: x\n\
+                 HA x MOD 2 = 0 AKKOR\n\
+                 \x20 KI: \"paros\"\n\
+                 KÜLÖNBEN HA x < 0 AKKOR\n\
+                 \x20 KI: \"negativ\"\n\
+                 KÜLÖNBEN\n\
+                 \x20 KI: \"paratlan\"\n\
+                 HA_VÉGE\n\
+                 PROGRAM_VÉGE\n";
+        assert_eq!(run(s, "4").unwrap(), "paros\n");
+        assert_eq!(run(s, "-3").unwrap(), "negativ\n");
+        assert_eq!(run(s, "7").unwrap(), "paratlan\n");
+        assert_eq!(run(s, "hét"), Err(RuntimeError::Input(String::from("hét"), String::from("EGÉSZ"))));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let s = "PROGRAM p\nVÁLTOZÓK:\nx: EGÉSZ\nx := 1 DIV x\nPROGRAM_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::DivByZero));
+
+        let s = "PROGRAM p\nVÁLTOZÓK:\nx: EGÉSZ\nx := IGAZ\nPROGRAM_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::Mismatch(String::from("EGÉSZ"), String::from("LOGIKAI"))));
+
+        let s = "PROGRAM p\nVÁLTOZÓK:\nx: EGÉSZ\nx := 2 ^ -1\nPROGRAM_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::Domain("^")));
+
+        let s = "PROGRAM p\nVÁLTOZÓK:\nx: EGÉSZ\nx := 0 ^ -1\nPROGRAM_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::DivByZero));
+
+        let s = "PROGRAM p\ny := 1\nPROGRAM_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::Undeclared(String::from("y"))));
+    }
+
+    #[test]
+    fn short_circuit() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 HA x /= 0 ÉS 10 DIV x > 1 AKKOR\n\
+                 \x20 KI: \"nagy\"\n\
+                 KÜLÖNBEN\n\
+                 \x20 KI: \"nulla\"\n\
+                 HA_VÉGE\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(run(s, "").unwrap(), "nulla\n");
+    }
+
+    #[test]
+    fn arrays() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 t, u: TÖMB[4: EGÉSZ]\n\
+                 \x20 i: EGÉSZ\n\
+                 i := 1\n\
+                 CIKLUS AMÍG i <= |t|\n\
+                 \x20 BE: t[i]\n\
+                 \x20 i := i + 1\n\
+                 CIKLUS_VÉGE\n\
+                 u := t\n\
+                 u[t[1]] := u[t[1]] * 10\n\
+                 KI: t, \" / \", u\n\
+                 KI: u[i]\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(run(s, "2\n3\n5\n7\n"), Err(RuntimeError::Index(5, 4)));
+
+        let s = s.replace("KI: u[i]\n", "");
+        assert_eq!(run(&s, "2\n3\n5\n7\n").unwrap(), "2 3 5 7 / 2 30 5 7\n");
+        assert_eq!(RuntimeError::Index(0, 4).message(Lang::En), "index 0 is out of bounds, valid indices are 1..4");
+    }
+
+    #[test]
+    fn transpose() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 a: TÖMB[2, 3: EGÉSZ]\n\
+                 \x20 b: TÖMB[3, 2: EGÉSZ]\n\
+                 \x20 i, j: EGÉSZ\n\
+                 i := 1\n\
+                 CIKLUS AMÍG i <= |a|\n\
+                 \x20 j := 1\n\
+                 \x20 CIKLUS AMÍG j <= |a[i]|\n\
+                 \x20 \x20 a[i, j] := i * 10 + j\n\
+                 \x20 \x20 b[j][i] := a[i, j]\n\
+                 \x20 \x20 j := j + 1\n\
+                 \x20 CIKLUS_VÉGE\n\
+                 \x20 i := i + 1\n\
+                 CIKLUS_VÉGE\n\
+                 KI: b\n\
+                 KI: b[2]\n\
+                 PROGRAM_VÉGE";
+        assert_eq!(run(s, "").unwrap(), "11 21\n12 22\n13 23\n12 22\n");
+        let s = s.replace("KI: b[2]\n", "KI: a[2, 4]\n");
+        assert_eq!(run(&s, ""), Err(RuntimeError::Index(4, 3)));
+    }
+
+    #[test]
+    fn procedures() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 a, b, n: EGÉSZ\n\
+                 \x20 t: TÖMB[3: EGÉSZ]\n\
+                 a := 1\n\
+                 b := 2\n\
+                 csere(a, b)\n\
+                 csere(t[1], t[3])\n\
+                 hossz(\"árvíz\", n)\n\
+                 KI: a, b, \" \", n, \" \", fakt(10)\n\
+                 PROGRAM_VÉGE\n\
+                 ELJÁRÁS csere(CÍM x, y: EGÉSZ)\n\
+                 VÁLTOZÓK:\n\
+                 \x20 s: EGÉSZ\n\
+                 \x20 s := x\n\
+                 \x20 x := y\n\
+                 \x20 y := s\n\
+                 ELJÁRÁS_VÉGE\n\
+                 ELJÁRÁS hossz(s: SZÖVEG, KI h: EGÉSZ)\n\
+                 \x20 h := h + |s|\n\
+                 ELJÁRÁS_VÉGE\n\
+                 FÜGGVÉNY fakt(n: EGÉSZ): EGÉSZ\n\
+                 \x20 HA n <= 1 AKKOR\n\
+                 \x20 \x20 fakt := 1\n\
+                 \x20 KÜLÖNBEN\n\
+                 \x20 \x20 fakt := n * fakt(n - 1)\n\
+                 \x20 HA_VÉGE\n\
+                 FÜGGVÉNY_VÉGE";
+        assert_eq!(run(s, "").unwrap(), "21 5 3628800\n");
+
+        let s = "PROGRAM p\nvegtelen(1)\nPROGRAM_VÉGE\n\
+                 ELJÁRÁS vegtelen(n: EGÉSZ)\n\
+                 \x20 vegtelen(n + 1)\n\
+                 ELJÁRÁS_VÉGE";
+        // like `plang run`, on a thread with room for every call
+        let deep = std::thread::Builder::new().stack_size(STACK_SIZE)
+            .spawn(move || run(s, ""))
+            .unwrap();
+        assert_eq!(deep.join().unwrap(), Err(RuntimeError::Recursion));
+
+        let s = "PROGRAM p\nVÁLTOZÓK:\nx: EGÉSZ\nx := f(1)\nPROGRAM_VÉGE\nELJÁRÁS f(a: EGÉSZ)\nELJÁRÁS_VÉGE";
+        assert_eq!(run(s, ""), Err(RuntimeError::NotFunction(String::from("f"))));
+    }
+
+    #[test]
+    fn references() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 i: EGÉSZ\n\
+                 \x20 t: TÖMB[3: EGÉSZ]\n\
+                 i := 1\n\
+                 g(i, t[i])\n\
+                 KI: i, \" \", t[1], \" \", t[2]\n\
+                 PROGRAM_VÉGE\n\
+                 ELJÁRÁS g(CÍM k, e: EGÉSZ)\n\
+                 \x20 k := 2\n\
+                 \x20 e := 9\n\
+                 ELJÁRÁS_VÉGE";
+        assert_eq!(run(s, "").unwrap(), "2 9 0\n");
+        assert_eq!(run(&s.replace("CÍM", "KI"), "").unwrap(), "2 9 0\n");
+
+        // both parameters are `x`, so each sees what the other is given
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 f(x, x)\n\
+                 KI: x\n\
+                 PROGRAM_VÉGE\n\
+                 ELJÁRÁS f(CÍM a, b: EGÉSZ)\n\
+                 \x20 a := 1\n\
+                 \x20 b := a + 1\n\
+                 \x20 KI: a\n\
+                 \x20 h(a)\n\
+                 ELJÁRÁS_VÉGE\n\
+                 ELJÁRÁS h(CÍM c: EGÉSZ)\n\
+                 \x20 c := c * 10\n\
+                 ELJÁRÁS_VÉGE";
+        assert_eq!(run(s, "").unwrap(), "2\n20\n");
+    }
+}
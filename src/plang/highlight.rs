@@ -0,0 +1,168 @@
+use super::ast::escape_html;
+use super::lexer::{ Lexer, doc_text };
+use super::tokenizer::TokenType;
+use super::variable::TypeName;
+
+/// Classes the CSS of highlighted listings can style, e.g.
+/// `.plang .kw { font-weight: bold; }`.
+pub const STYLE: &str = "\
+.plang .kw { font-weight: bold; color: #1a4f9c; }
+.plang .type { color: #7a3e9d; }
+.plang .op { color: #5a5a5a; }
+.plang .str { color: #2e7d32; }
+.plang .num { color: #b35c00; }
+.plang .comment { color: #7a7a7a; font-style: italic; }
+.plang .doc { color: #4e6e8e; font-style: italic; }
+.plang .error { color: #c62828; text-decoration: underline wavy; }";
+
+/// What a piece of the source is, as far as coloring goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Whitespace, line breaks and names: left as they are
+    Plain,
+    Keyword,
+    /// Keywords naming a type, like `EGÉSZ`
+    Type,
+    Operator,
+    /// String and character literals
+    Str,
+    Number,
+    Comment,
+    Doc,
+    /// Anything the lexer couldn't make sense of
+    Error
+}
+
+impl Class {
+    fn of(ttype: &TokenType, lexical: &str, text: &str) -> Class {
+        match ttype {
+            TokenType::Keyword if TypeName::is_type_word(lexical) => Class::Type,
+            TokenType::Keyword => Class::Keyword,
+            TokenType::Operator => Class::Operator,
+            TokenType::String | TokenType::Char => Class::Str,
+            TokenType::Int | TokenType::Float => Class::Number,
+            TokenType::Comment if doc_text(text).is_some() => Class::Doc,
+            TokenType::Comment => Class::Comment,
+            TokenType::Undefined => Class::Error,
+            _ => Class::Plain
+        }
+    }
+
+    /// Name of the CSS class, `None` for plain text.
+    pub fn css(&self) -> Option<&'static str> {
+        match self {
+            Class::Plain => None,
+            Class::Keyword => Some("kw"),
+            Class::Type => Some("type"),
+            Class::Operator => Some("op"),
+            Class::Str => Some("str"),
+            Class::Number => Some("num"),
+            Class::Comment => Some("comment"),
+            Class::Doc => Some("doc"),
+            Class::Error => Some("error")
+        }
+    }
+
+    /// SGR parameters of the terminal color, `None` for plain text.
+    fn ansi(&self) -> Option<&'static str> {
+        match self {
+            Class::Plain | Class::Operator => None,
+            Class::Keyword => Some("1;34"),
+            Class::Type => Some("35"),
+            Class::Str => Some("32"),
+            Class::Number => Some("33"),
+            Class::Comment => Some("2"),
+            Class::Doc => Some("2;3"),
+            Class::Error => Some("4;31")
+        }
+    }
+}
+
+/// Splits the whole text read by `lex` into pieces with their class.
+/// Put back together, the pieces give the text exactly.
+pub fn classify<'a>(mut lex: Lexer<'a>) -> Vec<(Class, &'a str)> {
+    lex.set_skipped(0);
+    let text = lex.text();
+    let mut pieces = vec![];
+    let mut at = 0;
+    for t in lex.by_ref() {
+        let span = t.tok.span;
+        if span.begin > at {
+            pieces.push((Class::Plain, &text[at..span.begin]));
+        }
+        // sval of comments and literals leaves out the delimiters
+        let token_text = &text[span.begin..span.end];
+        pieces.push((Class::of(&t.tok.ttype, &t.lex, t.tok.sval), token_text));
+        at = span.end;
+    }
+    if at < text.len() {
+        pieces.push((Class::Plain, &text[at..]));
+    }
+    pieces
+}
+
+/// The text colored with ANSI escape codes, for printing to a terminal.
+pub fn ansi(lex: Lexer) -> String {
+    let mut out = String::new();
+    for (class, text) in classify(lex) {
+        match class.ansi() {
+            Some(sgr) => out += &format!("\x1b[{}m{}\x1b[0m", sgr, text),
+            None => out += text
+        }
+    }
+    out
+}
+
+/// The highlighted text as a standalone HTML page.
+pub fn render_page(lex: Lexer, title: &str) -> String {
+    let mut page = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    page += &format!("<title>{}</title>\n", escape_html(title));
+    page += &format!("<style>\n{}\n</style>\n</head>\n<body>\n", STYLE);
+    page += &html(lex);
+    page += "</body>\n</html>\n";
+    page
+}
+
+/// The text as a `pre` element with `span`s of the `STYLE` classes.
+pub fn html(lex: Lexer) -> String {
+    let mut out = String::from("<pre class=\"plang\">");
+    for (class, text) in classify(lex) {
+        match class.css() {
+            Some(css) => out += &format!("<span class=\"{}\">{}</span>", css, escape_html(text)),
+            None => out += &escape_html(text)
+        }
+    }
+    out += "</pre>\n";
+    out
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes() {
+        let src = "HA x < 1,5 AKKOR ** ok\r\n  KI: \"a\" $ *** doc\n";
+        let pieces = classify(Lexer::from(src));
+        assert_eq!(pieces.iter().map(|p| p.1).collect::<String>(), src);
+
+        let colored: Vec<(Class, &str)> = pieces.into_iter()
+            .filter(|p| p.0 != Class::Plain)
+            .collect();
+        assert_eq!(colored, [
+            (Class::Keyword, "HA"), (Class::Operator, "<"), (Class::Number, "1,5"),
+            (Class::Keyword, "AKKOR"), (Class::Comment, "** ok"), (Class::Keyword, "KI"),
+            (Class::Operator, ":"), (Class::Str, "\"a\""), (Class::Error, "$"), (Class::Doc, "*** doc")
+        ]);
+    }
+
+    #[test]
+    fn output() {
+        let src = "x: EGÉSZ ** <n>\n";
+        assert_eq!(html(Lexer::from(src)),
+            "<pre class=\"plang\">x<span class=\"op\">:</span> <span class=\"type\">EGÉSZ</span> \
+             <span class=\"comment\">** &lt;n&gt;</span>\n</pre>\n");
+        assert_eq!(ansi(Lexer::from(src)), "x: \x1b[35mEGÉSZ\x1b[0m \x1b[2m** <n>\x1b[0m\n");
+    }
+}
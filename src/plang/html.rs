@@ -0,0 +1,248 @@
+use super::ast::*;
+use super::declarations::Declarations;
+
+const STYLE: &str = "\
+body { font-family: serif; }
+.plang { font-family: monospace; line-height: 1.4; }
+.plang .kw { font-weight: bold; font-variant: small-caps; }
+.plang .block { margin-left: 2em; }
+.plang .doc { font-family: serif; font-style: italic; }";
+
+/// Renders the program, followed by its subprograms, as a standalone
+/// HTML page.
+pub fn render_page(program: &Program) -> String {
+    let mut page = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    page += &format!("<title>{}</title>\n", escape_html(&program.name));
+    page += &format!("<style>\n{}\n</style>\n</head>\n<body>\n", STYLE);
+    page += &render_listing(program);
+    page += "</body>\n</html>\n";
+    page
+}
+
+/// Renders the program as a `div`, to be put into a page of one's own.
+pub fn render_listing(program: &Program) -> String {
+    let mut r = Renderer { out: String::new() };
+
+    r.out += "<div class=\"plang\">\n";
+    r.doc(&program.doc);
+    r.line(&format!("{} {}", kw("PROGRAM"), escape_html(&program.name)));
+    r.block(|r| {
+        r.decls(&program.decls);
+        r.statements(&program.body);
+    });
+    r.line(&kw("PROGRAM_VÉGE"));
+
+    for sub in &program.subprograms {
+        r.line("");
+        r.subprogram(sub);
+    }
+    r.out += "</div>\n";
+    r.out
+}
+
+fn kw(word: &str) -> String {
+    format!("<span class=\"kw\">{}</span>", word)
+}
+
+struct Renderer {
+    out: String
+}
+
+impl Renderer {
+    fn line(&mut self, content: &str) {
+        if content.is_empty() {
+            self.out += "<div class=\"line\">&nbsp;</div>\n";
+        } else {
+            self.out += &format!("<div class=\"line\">{}</div>\n", content);
+        }
+    }
+
+    /// A doc comment, shown as text before what it's about.
+    fn doc(&mut self, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            for line in doc.lines() {
+                self.out += &format!("<div class=\"line doc\">{}</div>\n", escape_html(line));
+            }
+        }
+    }
+
+    fn block<F: FnOnce(&mut Renderer)>(&mut self, f: F) {
+        self.out += "<div class=\"block\">\n";
+        f(self);
+        self.out += "</div>\n";
+    }
+
+    fn decls(&mut self, decls: &Declarations) {
+        if decls.variables.is_empty() {
+            return;
+        }
+        self.line(&format!("{}:", kw("VÁLTOZÓK")));
+        self.block(|r| {
+            for decl in &decls.variables {
+                r.doc(&decl.doc);
+                let names: Vec<String> = decl.names.iter().map(|n| escape_html(n)).collect();
+                r.line(&format!("{}: {}", names.join(", "), kw(&decl.vtype.name())));
+            }
+        });
+    }
+
+    fn subprogram(&mut self, sub: &Subprogram) {
+        // consecutive parameters of the same type and mode are listed
+        // together, as they are usually declared
+        let mut groups: Vec<(ParamMode, Vec<String>, String)> = vec![];
+        for p in &sub.params {
+            match groups.last_mut() {
+                Some((mode, names, t)) if *mode == p.mode && *t == p.ptype.name() => {
+                    names.push(escape_html(&p.name))
+                },
+                _ => groups.push((p.mode, vec![escape_html(&p.name)], p.ptype.name()))
+            }
+        }
+        let params: Vec<String> = groups.iter()
+            .map(|(mode, names, t)| match mode.keyword() {
+                Some(mode) => format!("{} {}: {}", kw(mode), names.join(", "), kw(t)),
+                None => format!("{}: {}", names.join(", "), kw(t))
+            })
+            .collect();
+
+        let (open, close) = match sub.result {
+            Some(_) => ("FÜGGVÉNY", "FÜGGVÉNY_VÉGE"),
+            None => ("ELJÁRÁS", "ELJÁRÁS_VÉGE")
+        };
+        let mut header = format!("{} {}({})", kw(open), escape_html(&sub.name), params.join(", "));
+        if let Some(t) = &sub.result {
+            header += &format!(": {}", kw(&t.name()));
+        }
+
+        self.doc(&sub.doc);
+        self.line(&header);
+        self.block(|r| {
+            r.decls(&sub.decls);
+            r.statements(&sub.body);
+        });
+        self.line(&kw(close));
+    }
+
+    fn statements(&mut self, block: &[Statement]) {
+        for s in block {
+            self.statement(s);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Assign(target, value) => {
+                self.line(&format!("{} := {}", target.render(), value.render()));
+            },
+            Statement::If { branches, otherwise } => {
+                for (i, (cond, body)) in branches.iter().enumerate() {
+                    let open = if i == 0 { kw("HA") } else { format!("{} {}", kw("KÜLÖNBEN"), kw("HA")) };
+                    self.line(&format!("{} {} {}", open, cond.render(), kw("AKKOR")));
+                    self.block(|r| r.statements(body));
+                }
+                if let Some(body) = otherwise {
+                    self.line(&kw("KÜLÖNBEN"));
+                    self.block(|r| r.statements(body));
+                }
+                self.line(&kw("HA_VÉGE"));
+            },
+            Statement::While(cond, body) => {
+                self.line(&format!("{} {} {}", kw("CIKLUS"), kw("AMÍG"), cond.render()));
+                self.block(|r| r.statements(body));
+                self.line(&kw("CIKLUS_VÉGE"));
+            },
+            Statement::Call(call) => self.line(&call.render()),
+            Statement::Output(args) => self.io_line("KI", args),
+            Statement::Input(args) => self.io_line("BE", args)
+        }
+    }
+
+    fn io_line(&mut self, word: &str, args: &[Expression]) {
+        let args: Vec<String> = args.iter().map(|a| a.render()).collect();
+        self.line(&format!("{}: {}", kw(word), args.join(", ")));
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::Parser;
+
+    fn expr(s: &str) -> String {
+        Parser::from(s).parse_expression().unwrap().render()
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(expr("(a + b) * c"), "(a + b) * c");
+        assert_eq!(expr("a + b * c"), "a + b * c");
+        assert_eq!(expr("\"<b>\" <= x"), "\"&lt;b&gt;\" &lt;= x");
+        assert_eq!(expr("|t[i] - 1|"), "|t[i] - 1|");
+        assert_eq!(expr("SIN x ÉS NEM b"), "SIN x ÉS NEM b");
+        assert_eq!(expr("f(1,'a')"), "f(1, 'a')");
+        assert_eq!(expr(r#""say \"hi\"\n" @ '\''"#), r#""say \"hi\"\n" @ '\''"#);
+    }
+
+    /// Rendering puts back just the parentheses that change the meaning.
+    #[test]
+    fn minimal_parentheses() {
+        for s in ["a - (b - c)", "a - b - c", "(a ^ b) ^ c", "a ^ b ^ c", "-a ^ b",
+                  "(a < b) = c", "NEM (a ÉS b) VAGY c", "a * -b"] {
+            let e = Parser::from(s).parse_expression().unwrap();
+            assert_eq!(e.render().replace("&lt;", "<"), s);
+        }
+        assert_eq!(expr("( (a) ) * (b * c)"), "a * (b * c)");
+        assert_eq!(expr("(a * b) + (c DIV d)"), "a * b + c DIV d");
+        assert_eq!(expr("(-a) ^ b"), "(-a) ^ b");
+        assert_eq!(expr("- (a * b)"), "-(a * b)");
+        assert_eq!(expr("(a+b)[i]"), "(a + b)[i]");
+    }
+
+    #[test]
+    fn listing() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 a, b: EGÉSZ\n\
+                 HA a < b AKKOR\n\
+                 \x20 KI: a\n\
+                 HA_VÉGE\n\
+                 PROGRAM_VÉGE\n";
+        let html = render_listing(&Parser::from(s).parse().unwrap());
+
+        let expect = "\
+<div class=\"plang\">
+<div class=\"line\"><span class=\"kw\">PROGRAM</span> p</div>
+<div class=\"block\">
+<div class=\"line\"><span class=\"kw\">VÁLTOZÓK</span>:</div>
+<div class=\"block\">
+<div class=\"line\">a, b: <span class=\"kw\">EGÉSZ</span></div>
+</div>
+<div class=\"line\"><span class=\"kw\">HA</span> a &lt; b <span class=\"kw\">AKKOR</span></div>
+<div class=\"block\">
+<div class=\"line\"><span class=\"kw\">KI</span>: a</div>
+</div>
+<div class=\"line\"><span class=\"kw\">HA_VÉGE</span></div>
+</div>
+<div class=\"line\"><span class=\"kw\">PROGRAM_VÉGE</span></div>
+</div>
+";
+        assert_eq!(html, expect);
+    }
+
+    #[test]
+    fn docs() {
+        let s = "*** Compute the <sum>.\n\
+                 *** Print it.\n\
+                 PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 (** the total *)\n\
+                 \x20 a: EGÉSZ\n\
+                 PROGRAM_VÉGE\n";
+        let html = render_listing(&Parser::from(s).parse().unwrap());
+        assert!(html.starts_with("<div class=\"plang\">\n\
+                                  <div class=\"line doc\">Compute the &lt;sum&gt;.</div>\n\
+                                  <div class=\"line doc\">Print it.</div>\n"));
+        assert!(html.contains("<div class=\"line doc\">the total</div>\n<div class=\"line\">a: "));
+    }
+}
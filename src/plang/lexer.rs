@@ -1,39 +1,12 @@
-use std::collections::HashSet;
-use super::tokenizer::{ Token, TokenType, Tokenizer };
-use lazy_static::*;
+use super::tokenizer::{ Token, TokenType, Tokenizer, Span, NumberPolicy };
+use super::dialect::Dialect;
+use super::unicode::nfc;
+use super::diagnostic::{ Diagnostic, Code, Arg, Message };
+use super::catalog::Phrase;
+use std::collections::HashMap;
 use std::fmt;
 
-macro_rules! set {
-    ( $($x:expr),* ) => {
-        {
-            let mut temp_set = HashSet::new();
-            $(temp_set.insert($x);)*
-            temp_set
-        }
-    };
-}
-
-lazy_static! {
-    static ref KEYWORDS: HashSet<&'static str> = set![
-        "program", "program_vege", "eljaras", "eljaras_vege", 
-        "fuggveny", "fuggveny_vege", "valtozok", "ha", "akkor", 
-        "ha_vege", "kulonben", "ciklus", "amig", "ciklus_vege", "egesz",
-        "valos", "szoveg", "karakter", "logikai", "igaz", "hamis",
-        "sv", "ki", "be", "sin", "cos", "tan", "log", "exp", "rnd",
-        "arcsin", "arccos", "arctan", "kis", "nagy", "kerek", "betu", 
-        "szam", "nem", "es", "vagy", "kifajl", "befajl", "megnyit", 
-        "lezar", "vege"
-    ];
-
-    static ref OPERATORS: HashSet<&'static str> = set![
-        ",", ":", "+", "-", "*", "/", "^", "@",
-        "div", "mod", "(", ")", "[", "]", "|", ":=", "=", "/=", 
-        "<", ">", "<=", ">="
-    ];
-}
-
-
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct LexerToken<'a> {
     pub tok: Token<'a>,
     pub lex: String
@@ -41,137 +14,378 @@ pub struct LexerToken<'a> {
 
 impl<'a> LexerToken<'a> {
     #[cfg(test)]
-    pub fn from(sval: &str, nval: f64, ttype: TokenType) -> LexerToken {
-        let lex = if ttype == TokenType::Keyword {
-            Lexer::deaccent(sval.to_lowercase())
-        } else {
-            String::new()
+    pub fn from(sval: &str, nval: f64, ttype: TokenType) -> LexerToken<'_> {
+        let lex = match ttype {
+            TokenType::Keyword => super::dialect::normalize(sval),
+            TokenType::Ident => nfc(sval).into_owned(),
+            TokenType::Operator => sval.to_string(),
+            _ => String::new()
         };
 
-        LexerToken { tok: Token { sval, nval, ttype}, lex }
+        LexerToken { tok: Token::from(sval, nval, ttype), lex }
     }
 }
 
-#[derive(Debug)]
+/// A token `Lexer::expect` can look for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind<'k> {
+    Ident,
+    /// An operator by its meaning, how it's written in the default
+    /// dialect.
+    Op(&'k str),
+    /// A keyword by its meaning, the lowercase, unaccented form it has
+    /// in the default dialect, e.g. `ha_vege`.
+    Keyword(&'k str)
+}
+
+/// A position of the lexer to go back to. Only valid for the lexer it
+/// was taken from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint(usize);
+
+#[derive(Debug, Clone)]
 pub struct Lexer<'a> {
     tok: Tokenizer<'a>,
-    
-    current: Token<'a>,
-    current_lex: String
+
+    /// Tokens read so far, the current one at `pos`. They're kept so the
+    /// lexer can be rewound. The last one is `EOF` once the text is over.
+    tokens: Vec<LexerToken<'a>>,
+    pos: usize,
+
+    /// `SKIP_*` flags of tokens stepped over without being returned.
+    skip: i32,
+
+    dialect: Dialect,
+
+    /// Names by their NFC form, as they were first written and where.
+    names: HashMap<String, (&'a str, Span)>,
+
+    diagnostics: Vec<Diagnostic>
 }
 
 impl<'a> Lexer<'a> {
+    pub const SKIP_COMMENTS: i32 = 0b00001;
+    pub const SKIP_LINES: i32    = 0b00010;
+
     pub fn from(s: &'a str) -> Self {
-        let options = Tokenizer::EOL_MATTERS | Tokenizer::CONCAT_SPECIAL;
-        let comment_defs = vec![("**", "")];
-        let mut tok = Tokenizer::with_comments(s, options, comment_defs);
-
-        let tok_it = tok.next();
-        let current = match tok_it {
-            Some(t) => t,
-            None => Token { 
-                sval: "", 
-                nval: 0.0, 
-                ttype: TokenType::Undefined 
-            } 
-        };
+        Lexer::with_dialect(s, Dialect::default())
+    }
 
-        Self { tok, current, current_lex: String::new() }
+    /// Lexer for the default dialect with numbers written as `numbers`
+    /// says.
+    pub fn with_numbers(s: &'a str, numbers: NumberPolicy) -> Self {
+        Lexer::with_dialect(s, Dialect::default().with_numbers(numbers))
     }
 
-    fn deaccent<S>(word: S) -> String where S: Into<String>
-    {
-        word.into().chars().map(|c| match c {
-            'á' => 'a',
-            'í' => 'i',
-            'é' => 'e',
-            'ó'|'ö'|'ő' => 'o',
-            'ú'|'ü'|'ű' => 'u',
-            _ => c
-        }).collect()
+    pub fn with_dialect(s: &'a str, dialect: Dialect) -> Self {
+        let options = Tokenizer::EOL_MATTERS | Tokenizer::CONCAT_SPECIAL;
+        let mut tok = Tokenizer::with_comments(s, options, dialect.comments().to_vec());
+        tok.set_number_policy(dialect.numbers);
+        tok.set_operators(dialect.operators());
+        tok.set_keywords(dialect.keywords());
+
+        let mut lx = Self { tok, tokens: vec![], pos: 0, skip: 0, dialect,
+            names: HashMap::new(), diagnostics: vec![] };
+        let first = lx.read();
+        lx.tokens.push(first);
+        lx
     }
 
-    fn step(&mut self) {
-        let tk_it = self.tok.next();
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
 
-        if tk_it.is_none() {
-            self.current.ttype = TokenType::EOF;
-        }
+    /// How the keyword meaning `meaning` is written in the dialect read.
+    pub fn keyword_name(&self, meaning: &str) -> String {
+        self.dialect.name(meaning)
+    }
 
-        let mut tk = tk_it.unwrap();
-        self.current_lex = match tk.ttype {
+    /// Reads the next token from the text.
+    fn read(&mut self) -> LexerToken<'a> {
+        let mut tk = match self.tok.next() {
+            Some(tk) => tk,
+            None => {
+                let mut tk = Token::from_str("", TokenType::EOF);
+                tk.span = self.tok.end_span();
+                return LexerToken { tok: tk, lex: String::new() };
+            }
+        };
+        let lex = match tk.ttype {
             TokenType::Special => {
-                if OPERATORS.contains(tk.sval) {
+                if let Some(meaning) = self.dialect.operator(tk.sval) {
                     tk.ttype = TokenType::Operator;
-                    // tk.ttype = TokenType::Keyword;
+                    meaning.to_string()
                 } else {
                     tk.ttype = TokenType::Undefined;
+                    self.diagnostics.push(Diagnostic::error(Code::UnknownOperator, tk.span, &[tk.sval]));
+                    String::new()
                 }
-                String::new()
             },
             TokenType::Word => {
-                let word  = Lexer::deaccent(tk.sval.to_lowercase());
-                if KEYWORDS.contains(word.as_str()) {
+                if let Some(meaning) = self.dialect.keyword(tk.sval) {
                     tk.ttype = TokenType::Keyword;
-                    word
+                    meaning.to_string()
                 } else {
                     tk.ttype = TokenType::Ident;
-                    String::new()
+                    self.name(&tk)
                 }
             },
             _ => String::new()
         };
 
-        self.current = tk;
+        LexerToken { tok: tk, lex }
     }
 
-    pub fn is_data(&self) -> bool {
-        match self.current.ttype {
-            TokenType::Char | TokenType::String | 
-            TokenType::Int  | TokenType::Float => true,
+    /// The NFC form of a name, so it's the same however the editor
+    /// encoded its accents. Using a name in a form different from the
+    /// first one is warned about, in case the form matters elsewhere.
+    fn name(&mut self, tk: &Token<'a>) -> String {
+        let name = nfc(tk.sval).into_owned();
+        match self.names.get(&name) {
+            Some(&(first, span)) if first != tk.sval => {
+                let d = Diagnostic::warning(Code::MixedForms, tk.span, &[&name])
+                    .with_secondary(span, Phrase::WrittenDifferently)
+                    .with_note(Phrase::CombiningMark);
+                self.diagnostics.push(d);
+            },
+            Some(_) => (),
+            None => {
+                self.names.insert(name.clone(), (tk.sval, tk.span));
+            }
+        }
+        name
+    }
 
+    fn is_skipped(&self, ttype: &TokenType) -> bool {
+        match ttype {
+            TokenType::Comment => self.skip & Self::SKIP_COMMENTS != 0,
+            TokenType::LineBreak => self.skip & Self::SKIP_LINES != 0,
             _ => false
-        } 
+        }
+    }
+
+    /// Index of the first token after `index` that isn't skipped, 
+    /// reading as many as needed.
+    fn index_after(&mut self, index: usize) -> usize {
+        let mut i = index;
+        loop {
+            if self.tokens[i].tok.ttype == TokenType::EOF {
+                return i;
+            }
+            i += 1;
+            if i == self.tokens.len() {
+                let tk = self.read();
+                self.tokens.push(tk);
+            }
+            if !self.is_skipped(&self.tokens[i].tok.ttype) {
+                return i;
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        self.pos = self.index_after(self.pos);
+    }
+
+    fn current(&self) -> &Token<'a> {
+        &self.tokens[self.pos].tok
+    }
+
+    /// Sets which kinds of tokens to step over, as `SKIP_*` flags. If the
+    /// current token is one of them, it's stepped over right away.
+    pub fn set_skipped(&mut self, skip: i32) {
+        self.skip = skip;
+        if self.is_skipped(&self.current().ttype) {
+            self.step();
+        }
+    }
+
+    /// The token `n` places ahead, `peek(0)` being the current one. 
+    /// Skipped tokens are not counted. Past the end of the text it's
+    /// always `EOF`.
+    pub fn peek(&mut self, n: usize) -> &LexerToken<'a> {
+        let mut i = self.pos;
+        for _ in 0..n {
+            i = self.index_after(i);
+        }
+        &self.tokens[i]
+    }
+
+    /// Doc comments right before the current token, each on a line of
+    /// its own, with their lines joined. A blank line or anything else in
+    /// between means they're not about this token.
+    pub fn docs(&self) -> Option<String> {
+        let mut docs = vec![];
+        let mut i = self.pos;
+        let mut blank = false;
+        while i > 0 {
+            i -= 1;
+            let tk = &self.tokens[i].tok;
+            match tk.ttype {
+                TokenType::LineBreak if blank => break,
+                TokenType::LineBreak => blank = true,
+                TokenType::Comment => {
+                    let own_line = i == 0 || self.tokens[i - 1].tok.ttype == TokenType::LineBreak;
+                    match doc_text(tk.sval) {
+                        Some(doc) if own_line => docs.push(doc),
+                        _ => break
+                    }
+                    blank = false;
+                },
+                _ => break
+            }
+        }
+        if docs.is_empty() {
+            return None;
+        }
+        docs.reverse();
+        Some(docs.join("\n"))
+    }
+
+    /// The whole text being read.
+    pub fn text(&self) -> &'a str {
+        self.tok.text()
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.pos)
+    }
+
+    /// Goes back to where the lexer was at `cp`. Diagnostics of the 
+    /// tokens read since are not repeated.
+    pub fn rewind(&mut self, cp: Checkpoint) {
+        self.pos = cp.0.min(self.tokens.len() - 1);
+    }
+
+    pub fn at(&self, kind: TokenKind) -> bool {
+        let current = &self.tokens[self.pos];
+        match kind {
+            TokenKind::Ident => current.tok.ttype == TokenType::Ident,
+            TokenKind::Op(op) => current.tok.ttype == TokenType::Operator && current.lex == op,
+            TokenKind::Keyword(kw) => {
+                current.tok.ttype == TokenType::Keyword && current.lex == kw
+            }
+        }
+    }
+
+    /// Steps over the current token if it's of the given `kind`, 
+    /// returning it. If not, it's left there.
+    pub fn expect(&mut self, kind: TokenKind) -> Result<LexerToken<'a>, Diagnostic> {
+        if !self.at(kind) {
+            let what: Arg = match kind {
+                TokenKind::Ident => Phrase::Name.into(),
+                TokenKind::Op(op) => format!("`{}`", op).into(),
+                TokenKind::Keyword(kw) => format!("`{}`", self.keyword_name(kw)).into()
+            };
+            return Err(self.expected(what));
+        }
+        let tk = self.tokens[self.pos].clone();
+        self.step();
+        Ok(tk)
+    }
+
+    /// Error for finding the current token where `what` should be.
+    pub fn expected(&self, what: Arg) -> Diagnostic {
+        Diagnostic::error(Code::Expected, self.span(), &[])
+            .with_arg(what.clone())
+            .with_arg(self.describe())
+            .with_label(Message::new(Phrase::ExpectedHere, what))
+    }
+
+    /// Problems found by the lexer and its tokenizer in the tokens 
+    /// read so far.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        let mut diags = self.tok.take_diagnostics();
+        diags.append(&mut self.diagnostics);
+        diags
+    }
+
+    pub fn is_data(&self) -> bool {
+        matches!(self.current().ttype, 
+            TokenType::Char | TokenType::String | 
+            TokenType::Int  | TokenType::Float)
     }
 
     pub fn is_ident(&self) -> bool {
-        return self.current.ttype == TokenType::Ident;
+        self.current().ttype == TokenType::Ident
     }
 
     pub fn is_keyword(&self) -> bool {
-        return self.current.ttype == TokenType::Keyword;
+        self.current().ttype == TokenType::Keyword
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.current().ttype == TokenType::EOF
+    }
+
+    pub fn ttype(&self) -> TokenType {
+        self.current().ttype.clone()
+    }
+
+    /// How the current token is referred to in error messages.
+    pub fn describe(&self) -> Arg {
+        let current = self.current();
+        match current.ttype {
+            TokenType::EOF => Phrase::EndOfFile.into(),
+            TokenType::LineBreak => Phrase::EndOfLine.into(),
+            TokenType::String => format!("\"{}\"", current.sval).into(),
+            TokenType::Char => format!("'{}'", current.sval).into(),
+            _ => format!("`{}`", current.sval).into()
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.current().span
     }
 
     pub fn sval(&self) -> &'a str {
-        return self.current.sval;
+        self.current().sval
+    }
+
+    /// Decoded content of a string or character literal, the token
+    /// text for anything else.
+    pub fn value(&self) -> &str {
+        let current = self.current();
+        current.value.as_deref().unwrap_or(current.sval)
+    }
+
+    pub fn cval(&self) -> Option<char> {
+        self.current().cval
     }
 
     pub fn nval(&self) -> f64 {
-        return self.current.nval;
+        self.current().nval
     }
 
     pub fn fval(&self) -> f64 {
-        return self.current.nval;
+        self.current().nval
     }
 
     pub fn ival(&self) -> i64 {
-        return self.current.nval as i64;
+        self.current().nval as i64
     }
 
     pub fn lexical(&self) -> String {
-        return self.current_lex.clone();
+        self.tokens[self.pos].lex.clone()
     }
 }
 
+/// Text of a doc comment, one whose text starts with one more `*`, like
+/// `*** text` or `(** text *)`. More than one is a plain comment, as
+/// in a line of stars.
+pub fn doc_text(comment: &str) -> Option<String> {
+    let text = comment.strip_prefix('*').filter(|t| !t.starts_with('*'))?;
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    Some(lines.join("\n").trim().to_string())
+}
+
 impl<'a> Iterator for Lexer<'a> {
     type Item = LexerToken<'a>;
 
     fn next(&mut self) -> Option<LexerToken<'a>> {
-        let rv = match self.current.ttype {
-            TokenType::EOF => Option::from(None),
-            _ => Option::from(LexerToken { 
-                tok: self.current.clone(), lex: self.current_lex.clone()
-            })
+        let rv = match self.current().ttype {
+            TokenType::EOF => None,
+            _ => Some(self.tokens[self.pos].clone())
         };
         self.step();
         rv
@@ -181,12 +395,13 @@ impl<'a> Iterator for Lexer<'a> {
 impl<'a> fmt::Debug for LexerToken<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.tok.ttype {
-            TokenType::Word => {
+            TokenType::Keyword => {
                 f.debug_struct("LexerToken")
                 .field("sval", &self.tok.sval)
                 .field("nval", &self.tok.nval)
                 .field("type", &self.tok.ttype)
                 .field("lexical", &self.lex)
+                .field("at", &format_args!("{}", self.tok.span))
                 .finish()
             } 
             _ =>  {
@@ -194,8 +409,86 @@ impl<'a> fmt::Debug for LexerToken<'a> {
                 .field("sval", &self.tok.sval)
                 .field("nval", &self.tok.nval)
                 .field("type", &self.tok.ttype)
+                .field("at", &format_args!("{}", self.tok.span))
                 .finish()
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::diagnostic::Severity;
+    use super::super::binary_operator::BinaryOperator;
+
+    #[test]
+    fn peek_and_rewind() {
+        let mut lx = Lexer::from("x, y: EGÉSZ ** names\nx := 1");
+        lx.set_skipped(Lexer::SKIP_COMMENTS);
+
+        assert_eq!(lx.peek(1).tok.sval, ",");
+        assert_eq!(lx.peek(4).lex, "egesz");
+        assert_eq!(lx.peek(5).tok.ttype, TokenType::LineBreak);
+        assert_eq!(lx.peek(100).tok.ttype, TokenType::EOF);
+        assert_eq!(lx.sval(), "x");
+
+        let cp = lx.checkpoint();
+        let names: Vec<&str> = lx.by_ref().take(5).map(|t| t.tok.sval).collect();
+        assert_eq!(names, ["x", ",", "y", ":", "EGÉSZ"]);
+        lx.rewind(cp);
+        assert_eq!(lx.sval(), "x");
+
+        lx.set_skipped(Lexer::SKIP_COMMENTS | Lexer::SKIP_LINES);
+        assert_eq!(lx.peek(6).tok.sval, ":=");
+    }
+
+    #[test]
+    fn expect() {
+        let mut lx = Lexer::from("HA x AKKOR");
+        assert!(lx.expect(TokenKind::Keyword("ha")).is_ok());
+        assert_eq!(lx.expect(TokenKind::Ident).unwrap().tok.sval, "x");
+
+        let d = lx.expect(TokenKind::Op(":=")).unwrap_err();
+        assert_eq!(d.code, Code::Expected);
+        assert_eq!(d.args, [Arg::from("`:=`"), Arg::from("`AKKOR`")]);
+        assert!(lx.at(TokenKind::Keyword("akkor")));
+    }
+
+    #[test]
+    fn normalization() {
+        let lx = Lexer::from("VA\u{301}LTOZO\u{301}K: o\u{308}sszeg, összeg, o\u{308}sszeg");
+        assert_eq!(lx.lexical(), "valtozok");
+        let names: Vec<String> = lx.filter(|t| t.tok.ttype == TokenType::Ident)
+            .map(|t| t.lex)
+            .collect();
+        assert_eq!(names, ["összeg", "összeg", "összeg"]);
+
+        let mut lx = Lexer::from("o\u{308}sszeg, összeg, o\u{308}sszeg");
+        lx.by_ref().count();
+        let diags = lx.take_diagnostics();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, Code::MixedForms);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!((diags[0].span().begin, diags[0].context.secondary[0].span.begin), (10, 0));
+    }
+
+    #[test]
+    fn dialects() {
+        let mut lx = Lexer::with_dialect("IF x THEN ha", Dialect::english());
+        assert!(lx.expect(TokenKind::Keyword("ha")).is_ok());
+        assert_eq!(lx.expect(TokenKind::Ident).unwrap().tok.sval, "x");
+
+        let d = lx.expect(TokenKind::Keyword("kulonben")).unwrap_err();
+        assert_eq!(d.args[0], Arg::from("`ELSE`"));
+        lx.next();
+        assert_eq!(lx.ttype(), TokenType::Ident);
+
+        let d = Dialect::default().with_operator("<>", BinaryOperator::Ne).with_comment("{", "}");
+        let tokens: Vec<_> = Lexer::with_dialect("a <> b { note }", d)
+            .map(|t| t.tok.ttype)
+            .collect();
+        assert_eq!(tokens, [TokenType::Ident, TokenType::Operator, TokenType::Ident, TokenType::Comment]);
+    }
+}
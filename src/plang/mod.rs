@@ -5,6 +5,33 @@ mod variable;
 mod environment;
 mod unary_operator;
 mod binary_operator;
+mod ast;
+mod parser;
+mod types;
+mod diagnostic;
+mod checker;
+mod catalog;
+mod cst;
+mod dialect;
+mod unicode;
+mod source;
+pub mod highlight;
+pub mod html;
+pub mod structogram;
 
-pub use self::lexer::{ Lexer, LexerToken };
-pub use self::tokenizer::{ Tokenizer, Token, TokenType };
\ No newline at end of file
+pub use self::lexer::{ Lexer, LexerToken, TokenKind, Checkpoint, doc_text };
+pub use self::tokenizer::{ Tokenizer, Token, TokenType, Span, NumberPolicy };
+pub use self::declarations::Declarations;
+pub use self::variable::{ Type, TypeName, VarDecl };
+pub use self::unary_operator::UnaryOperator;
+pub use self::binary_operator::{ BinaryOperator, Assoc };
+pub use self::ast::*;
+pub use self::parser::{ Parser, ParseResult };
+pub use self::environment::{ Environment, RuntimeError, RunResult, STACK_SIZE };
+pub use self::types::{ IntType, FloatType, StringType, CharType, BoolType };
+pub use self::diagnostic::{ Diagnostic, Diagnostics, Code, Severity, Label, Context, Message, Arg };
+pub use self::catalog::{ Lang, Phrase };
+pub use self::checker::check;
+pub use self::dialect::Dialect;
+pub use self::source::{ Source, Encoding, DecodeError };
+pub use self::cst::{ parse_cst, SyntaxNode, SyntaxToken, NodeKind, Element, Trivia };
@@ -0,0 +1,911 @@
+use super::lexer::{ Lexer, TokenKind };
+use super::tokenizer::{ TokenType, Span };
+use super::ast::*;
+use super::declarations::Declarations;
+use super::variable::{ VarDecl, TypeName };
+use super::unary_operator::UnaryOperator;
+use super::binary_operator::{ BinaryOperator, Assoc };
+use super::diagnostic::{ Diagnostic, Diagnostics, Code, Arg };
+use super::catalog::Phrase;
+
+pub type ParseResult<T> = Result<T, Diagnostic>;
+
+/// Keywords closing a block. Seeing one that doesn't close the block
+/// being parsed is reported, and its line skipped.
+const BLOCK_ENDS: [&str; 6] = [
+    "program_vege", "eljaras_vege", "fuggveny_vege",
+    "ha_vege", "kulonben", "ciklus_vege"
+];
+
+pub struct Parser<'a> {
+    lex: Lexer<'a>,
+    diags: Diagnostics
+}
+
+impl<'a> Parser<'a> {
+    pub fn from(s: &'a str) -> Parser<'a> {
+        Parser::new(Lexer::from(s))
+    }
+
+    pub fn new(lex: Lexer<'a>) -> Parser<'a> {
+        let mut parser = Parser { lex, diags: Diagnostics::new() };
+        parser.lex.set_skipped(Lexer::SKIP_COMMENTS);
+        parser
+    }
+
+    /// Parses a whole file, failing with everything found wrong in it
+    /// if there was any error.
+    pub fn parse(self) -> Result<Program, Diagnostics> {
+        match self.parse_with_diagnostics() {
+            (Some(p), diags) if !diags.has_errors() => Ok(p),
+            (_, diags) => Err(diags)
+        }
+    }
+
+    /// Parses a whole file: one `PROGRAM` block and any number of
+    /// `ELJÁRÁS`/`FÜGGVÉNY` definitions before or after it. Parsing goes
+    /// on after errors, so if there are any, the program may be partial.
+    pub fn parse_with_diagnostics(mut self) -> (Option<Program>, Diagnostics) {
+        let mut program: Option<Program> = None;
+        let mut subprograms: Vec<Subprogram> = vec![];
+
+        self.skip_lines();
+        while !self.lex.is_eof() {
+            if self.at_keyword("program") {
+                let span = self.lex.span();
+                let p = self.parse_program();
+                if program.is_none() {
+                    program = Some(p);
+                } else {
+                    self.diags.push(Diagnostic::error(Code::DupProgram, span, &[]));
+                }
+            }
+            else if self.at_keyword("eljaras") || self.at_keyword("fuggveny") {
+                let sub = self.parse_subprogram();
+                match subprograms.iter().find(|s| s.name == sub.name) {
+                    Some(prev) => {
+                        let d = Diagnostic::error(Code::DupSubprogram, sub.span, &[&sub.name])
+                            .with_secondary(prev.span, Phrase::FirstDefinedHere);
+                        self.diags.push(d);
+                    },
+                    None if sub.name.is_empty() => (),
+                    None => subprograms.push(sub)
+                }
+            }
+            else {
+                let d = self.error(self.keyword_arg("program"));
+                self.report(d);
+                self.sync();
+            }
+            self.skip_lines();
+        }
+
+        match program.as_mut() {
+            Some(p) => p.subprograms = subprograms,
+            None => self.diags.push(Diagnostic::error(Code::MissingProgram, self.lex.span(), &[]))
+        }
+
+        let lex_diags = self.lex.take_diagnostics();
+        self.diags.extend(lex_diags);
+        (program, self.diags)
+    }
+
+    fn parse_program(&mut self) -> Program {
+        let open = self.lex.span();
+        let doc = self.lex.docs();
+        self.advance();
+
+        let name = match self.expect_ident() {
+            Ok(name) => name,
+            Err(d) => {
+                self.report(d);
+                self.sync();
+                String::new()
+            }
+        };
+        self.end_line();
+
+        let decls = self.parse_decls();
+        let body = self.parse_block(&["program_vege"], open);
+        if self.at_keyword("program_vege") {
+            self.advance();
+        }
+
+        Program { name, decls, body, subprograms: vec![], doc }
+    }
+
+    fn parse_subprogram(&mut self) -> Subprogram {
+        let open = self.lex.span();
+        let is_function = self.at_keyword("fuggveny");
+        let doc = self.lex.docs();
+        self.advance();
+
+        let mut sub = Subprogram {
+            name: String::new(),
+            span: self.lex.span(),
+            params: vec![],
+            result: None,
+            decls: Declarations::new(),
+            body: vec![],
+            doc
+        };
+
+        if let Err(d) = self.parse_signature(&mut sub, is_function) {
+            self.report(d);
+            self.sync();
+        }
+        self.end_line();
+
+        sub.decls = self.parse_decls();
+        for p in &sub.params {
+            if let Some(span) = sub.decls.span_of(&p.name) {
+                let d = Diagnostic::error(Code::DupName, span, &[&p.name])
+                    .with_label(Phrase::DeclaredAgain)
+                    .with_secondary(p.span, Phrase::ParamDeclaredHere);
+                self.diags.push(d);
+            }
+        }
+
+        let end = if is_function { "fuggveny_vege" } else { "eljaras_vege" };
+        sub.body = self.parse_block(&[end], open);
+        if self.at_keyword(end) {
+            self.advance();
+        }
+
+        sub
+    }
+
+    /// `name(a, b: TYPE, KI c: TYPE, CÍM d: TYPE)`, followed by `: TYPE`
+    /// for functions
+    fn parse_signature(&mut self, sub: &mut Subprogram, is_function: bool) -> ParseResult<()> {
+        sub.span = self.lex.span();
+        sub.name = self.expect_ident()?;
+        self.expect_op("(")?;
+
+        if !self.at_op(")") {
+            loop {
+                let mode = if self.at_keyword("ki") {
+                    ParamMode::Out
+                } else if self.at_keyword("cim") {
+                    ParamMode::Ref
+                } else {
+                    ParamMode::In
+                };
+                if mode != ParamMode::In {
+                    self.advance();
+                }
+                let decl = VarDecl::parse(&mut self.lex)?;
+                for (name, span) in decl.names.into_iter().zip(decl.spans) {
+                    if let Some(prev) = sub.params.iter().find(|p| p.name == name) {
+                        let d = Diagnostic::error(Code::DupName, span, &[&name])
+                            .with_label(Phrase::DeclaredAgain)
+                            .with_secondary(prev.span, Phrase::FirstDeclaredHere);
+                        self.diags.push(d);
+                    }
+                    sub.params.push(Param { name, ptype: decl.vtype.clone(), mode, span });
+                }
+
+                if !self.at_op(",") { break; }
+                self.advance();
+            }
+        }
+        self.expect_op(")")?;
+
+        if is_function {
+            self.expect_op(":")?;
+            sub.result = Some(self.expect_type()?);
+        }
+        Ok(())
+    }
+
+    /// Parses an optional `VÁLTOZÓK:` block.
+    fn parse_decls(&mut self) -> Declarations {
+        let mut decls = Declarations::new();
+        if !self.at_keyword("valtozok") {
+            return decls;
+        }
+        self.advance();
+        if let Err(d) = self.expect_op(":") {
+            self.report(d);
+            self.sync();
+        }
+        self.end_line();
+
+        while self.is_decl_start() {
+            match VarDecl::parse(&mut self.lex) {
+                Ok(decl) => {
+                    if let Err(d) = decls.push(decl) {
+                        self.diags.push(d);
+                    }
+                },
+                Err(d) => {
+                    self.report(d);
+                    self.sync();
+                }
+            }
+            self.end_line();
+        }
+
+        decls
+    }
+
+    /// A declaration starts with `name ,` or `name :`, or `name TYPE` if
+    /// the colon is missing. Any other line starting with a name is the
+    /// first statement.
+    fn is_decl_start(&mut self) -> bool {
+        if !self.lex.is_ident() {
+            return false;
+        }
+        let next = self.lex.peek(1);
+        match next.tok.ttype {
+            TokenType::Operator => next.lex == "," || next.lex == ":",
+            TokenType::Keyword => TypeName::is_type_word(&next.lex),
+            _ => false
+        }
+    }
+
+    /// Parses statements until one of the `terminators` keywords, which
+    /// is left unconsumed. If the file ends first, the last terminator is
+    /// reported missing from the block opened at `open`.
+    fn parse_block(&mut self, terminators: &[&str], open: Span) -> Block {
+        let mut block = vec![];
+        loop {
+            if self.lex.is_eof() {
+                let end = terminators.last().copied().unwrap_or_default();
+                let d = self.error(self.keyword_arg(end))
+                    .with_secondary(open, Phrase::BlockOpenedHere);
+                self.report(d);
+                return block;
+            }
+            if self.lex.is_keyword() {
+                let lex = self.lex.lexical();
+                if terminators.contains(&lex.as_str()) {
+                    return block;
+                }
+                if BLOCK_ENDS.contains(&lex.as_str()) {
+                    let d = Diagnostic::error(Code::Unexpected, self.lex.span(), &[])
+                        .with_arg(self.lex.describe())
+                        .with_secondary(open, Phrase::InsideBlock);
+                    self.report(d);
+                    self.advance();
+                    self.sync();
+                    self.skip_lines();
+                    continue;
+                }
+            }
+
+            match self.parse_statement() {
+                Ok(s) => block.push(s),
+                Err(d) => {
+                    self.report(d);
+                    self.sync();
+                }
+            }
+            self.end_line();
+        }
+    }
+
+    fn parse_statement(&mut self) -> ParseResult<Statement> {
+        if self.at_keyword("ha") {
+            return Ok(self.parse_if());
+        }
+        if self.at_keyword("ciklus") {
+            let open = self.lex.span();
+            self.advance();
+            self.expect_keyword("amig")?;
+            let cond = self.parse_header(None);
+            let body = self.parse_block(&["ciklus_vege"], open);
+            if self.at_keyword("ciklus_vege") {
+                self.advance();
+            }
+            return Ok(Statement::While(cond, body));
+        }
+        if self.at_keyword("ki") || self.at_keyword("be") {
+            let is_output = self.at_keyword("ki");
+            self.advance();
+            if self.at_op(":") {
+                self.advance();
+            }
+
+            let mut args = vec![];
+            loop {
+                let e = self.parse_expression()?;
+                if !is_output && !e.is_assignable() {
+                    return Err(Diagnostic::error(Code::NotAssignable, e.span, &[])
+                        .with_label(Phrase::NeedsVariable));
+                }
+                args.push(e);
+
+                if !self.at_op(",") { break; }
+                self.advance();
+            }
+
+            if is_output {
+                return Ok(Statement::Output(args));
+            }
+            return Ok(Statement::Input(args));
+        }
+        if self.lex.is_ident() {
+            let target = self.parse_postfix()?;
+            if self.at_op(":=") {
+                if !target.is_assignable() {
+                    return Err(Diagnostic::error(Code::NotAssignable, target.span, &[]));
+                }
+                self.advance();
+                let value = self.parse_expression()?;
+                return Ok(Statement::Assign(target, value));
+            }
+            return match target.kind {
+                ExprKind::Call(_, _) => Ok(Statement::Call(target)),
+                _ => Err(self.error("`:=`".into()))
+            };
+        }
+
+        Err(self.error(Phrase::Statement.into()))
+    }
+
+    fn parse_if(&mut self) -> Statement {
+        let mut branches = vec![];
+        let mut otherwise = None;
+
+        let open = self.lex.span();
+        self.advance();
+        loop {
+            let cond = self.parse_header(Some("akkor"));
+            let body = self.parse_block(&["kulonben", "ha_vege"], open);
+            branches.push((cond, body));
+
+            if !self.at_keyword("kulonben") {
+                break;
+            }
+            self.advance();
+            if self.at_keyword("ha") {
+                self.advance();
+                continue;
+            }
+            self.end_line();
+            otherwise = Some(self.parse_block(&["ha_vege"], open));
+            break;
+        }
+        if self.at_keyword("ha_vege") {
+            self.advance();
+        }
+
+        Statement::If { branches, otherwise }
+    }
+
+    /// Condition of `HA` and `CIKLUS AMÍG`, with the keyword closing it
+    /// and the line break. A broken condition is reported and replaced
+    /// with `HAMIS`, so the block under it still gets parsed.
+    fn parse_header(&mut self, closing: Option<&str>) -> Expression {
+        let begin = self.lex.span();
+        let mut result = self.parse_expression();
+        if let (Ok(_), Some(kw)) = (&result, closing) {
+            if let Err(d) = self.expect_keyword(kw) {
+                result = Err(d);
+            }
+        }
+
+        let cond = match result {
+            Ok(cond) => cond,
+            Err(d) => {
+                self.report(d);
+                self.sync();
+                Expression::new(ExprKind::Literal(Literal::Bool(false)), begin)
+            }
+        };
+        self.end_line();
+        cond
+    }
+
+    pub fn parse_expression(&mut self) -> ParseResult<Expression> {
+        self.parse_binary(1)
+    }
+
+    /// Precedence climbing: parses operands joined by binary operators
+    /// of at least `min` precedence.
+    fn parse_binary(&mut self, min: u8) -> ParseResult<Expression> {
+        let mut lhs = self.parse_unary()?;
+        let mut chained: Option<u8> = None;
+
+        while let Some(op) = self.binary_op() {
+            let prec = op.precedence();
+            // a non-associative operator after one of the same level is
+            // left for the caller to reject
+            if prec < min || chained == Some(prec) {
+                break;
+            }
+            self.advance();
+
+            let next = match op.assoc() {
+                Assoc::Right => prec,
+                Assoc::Left | Assoc::None => prec + 1
+            };
+            let rhs = self.parse_binary(next)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression::new(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+
+            if op.assoc() == Assoc::None {
+                chained = Some(prec);
+            }
+        }
+        Ok(lhs)
+    }
+
+    /// Prefix operators take an operand of higher precedence than theirs,
+    /// so `-a^b` is `-(a^b)` but `-a*b` is `(-a)*b`.
+    fn parse_unary(&mut self) -> ParseResult<Expression> {
+        let op = if self.at_op("-") {
+            Some(UnaryOperator::Minus)
+        } else if self.lex.is_keyword() {
+            UnaryOperator::from_lexical(&self.lex.lexical())
+        } else {
+            None
+        };
+
+        match op {
+            Some(op) => {
+                let begin = self.lex.span();
+                self.advance();
+                let e = self.parse_binary(UnaryOperator::PRECEDENCE + 1)?;
+                let span = begin.to(e.span);
+                Ok(Expression::new(ExprKind::Unary(op, Box::new(e)), span))
+            },
+            None => self.parse_postfix()
+        }
+    }
+
+    fn parse_postfix(&mut self) -> ParseResult<Expression> {
+        let mut e = self.parse_primary()?;
+        while self.at_op("[") {
+            self.advance();
+            // `t[i, j]` is the same as `t[i][j]`
+            let mut indices = vec![self.parse_expression()?];
+            while self.at_op(",") {
+                self.advance();
+                indices.push(self.parse_expression()?);
+            }
+            let close = self.lex.span();
+            self.expect_op("]")?;
+            let span = e.span.to(close);
+            for idx in indices {
+                e = Expression::new(ExprKind::Binary(BinaryOperator::Bracket, Box::new(e), Box::new(idx)), span);
+            }
+        }
+        Ok(e)
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expression> {
+        let begin = self.lex.span();
+        let kind = match self.lex.ttype() {
+            TokenType::Int => ExprKind::Literal(Literal::Int(self.lex.ival())),
+            TokenType::Float => ExprKind::Literal(Literal::Float(self.lex.fval())),
+            TokenType::String => ExprKind::Literal(Literal::String(self.lex.value().to_string())),
+            TokenType::Char => match self.lex.cval() {
+                Some(c) => ExprKind::Literal(Literal::Char(c)),
+                None => return Err(Diagnostic::error(Code::InvalidChar, begin, &[]))
+            },
+            TokenType::Keyword if self.at_keyword("igaz") => ExprKind::Literal(Literal::Bool(true)),
+            TokenType::Keyword if self.at_keyword("hamis") => ExprKind::Literal(Literal::Bool(false)),
+            TokenType::Ident => {
+                let name = self.lex.lexical();
+                self.advance();
+                if !self.at_op("(") {
+                    return Ok(Expression::new(ExprKind::Variable(name), begin));
+                }
+                self.advance();
+
+                let mut args = vec![];
+                if !self.at_op(")") {
+                    args.push(self.parse_expression()?);
+                    while self.at_op(",") {
+                        self.advance();
+                        args.push(self.parse_expression()?);
+                    }
+                }
+                let close = self.lex.span();
+                self.expect_op(")")?;
+                return Ok(Expression::new(ExprKind::Call(name, args), begin.to(close)));
+            },
+            TokenType::Operator if self.at_op("(") => {
+                self.advance();
+                let mut e = self.parse_expression()?;
+                let close = self.lex.span();
+                self.expect_op(")")?;
+                e.span = begin.to(close);
+                return Ok(e);
+            },
+            TokenType::Operator if self.at_op("|") => {
+                self.advance();
+                let e = self.parse_expression()?;
+                let close = self.lex.span();
+                self.expect_op("|")?;
+                let kind = ExprKind::Unary(UnaryOperator::Pipe, Box::new(e));
+                return Ok(Expression::new(kind, begin.to(close)));
+            },
+            _ => return Err(self.error(Phrase::Expression.into()))
+        };
+
+        self.advance();
+        Ok(Expression::new(kind, begin))
+    }
+
+    fn binary_op(&self) -> Option<BinaryOperator> {
+        match self.lex.ttype() {
+            TokenType::Operator | TokenType::Keyword => BinaryOperator::from_lexical(&self.lex.lexical()),
+            _ => None
+        }
+    }
+
+    fn skip_lines(&mut self) {
+        while self.lex.ttype() == TokenType::LineBreak {
+            self.lex.next();
+        }
+    }
+
+    fn advance(&mut self) {
+        self.lex.next();
+    }
+
+    /// Skips the rest of the line after an error.
+    fn sync(&mut self) {
+        while self.lex.ttype() != TokenType::LineBreak && !self.lex.is_eof() {
+            self.advance();
+        }
+    }
+
+    /// Records an error, unless it's about a token the lexer already
+    /// reported as invalid.
+    fn report(&mut self, d: Diagnostic) {
+        if self.lex.ttype() == TokenType::Undefined && d.span() == self.lex.span() {
+            return;
+        }
+        self.diags.push(d);
+    }
+
+    fn at_keyword(&self, kw: &str) -> bool {
+        self.lex.is_keyword() && self.lex.lexical() == kw
+    }
+
+    fn at_op(&self, op: &str) -> bool {
+        self.lex.at(TokenKind::Op(op))
+    }
+
+    /// A keyword as it's referred to in messages, by its meaning.
+    fn keyword_arg(&self, kw: &str) -> Arg {
+        format!("`{}`", self.lex.keyword_name(kw)).into()
+    }
+
+    fn expect_keyword(&mut self, kw: &str) -> ParseResult<()> {
+        self.lex.expect(TokenKind::Keyword(kw)).map(|_| ())
+    }
+
+    fn expect_op(&mut self, op: &str) -> ParseResult<()> {
+        self.lex.expect(TokenKind::Op(op)).map(|_| ())
+    }
+
+    fn expect_ident(&mut self) -> ParseResult<String> {
+        self.lex.expect(TokenKind::Ident).map(|t| t.lex)
+    }
+
+    fn expect_type(&mut self) -> ParseResult<TypeName> {
+        TypeName::parse(&mut self.lex)
+    }
+
+    /// A statement must be followed by a line break (or the end of file).
+    /// Blank and comment-only lines after it are skipped.
+    fn expect_eol(&mut self) -> ParseResult<()> {
+        match self.lex.ttype() {
+            TokenType::LineBreak => {
+                self.skip_lines();
+                Ok(())
+            },
+            TokenType::EOF => Ok(()),
+            _ => Err(self.error(Phrase::EndOfLine.into()))
+        }
+    }
+
+    /// Like `expect_eol`, but reports the error and skips the rest of
+    /// the line.
+    fn end_line(&mut self) {
+        if let Err(d) = self.expect_eol() {
+            self.report(d);
+            self.sync();
+            self.skip_lines();
+        }
+    }
+
+    fn error(&self, expected: Arg) -> Diagnostic {
+        self.lex.expected(expected)
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dialect::Dialect;
+
+    fn var(name: &str) -> Expression {
+        ExprKind::Variable(String::from(name)).into()
+    }
+
+    fn int(n: i64) -> Expression {
+        ExprKind::Literal(Literal::Int(n)).into()
+    }
+
+    fn bin(op: BinaryOperator, a: Expression, b: Expression) -> Expression {
+        ExprKind::Binary(op, Box::new(a), Box::new(b)).into()
+    }
+
+    fn expr(s: &str) -> Expression {
+        Parser::from(s).parse_expression().unwrap()
+    }
+
+    fn errors(s: &str) -> Vec<(Code, usize, usize)> {
+        Parser::from(s).parse().unwrap_err().iter()
+            .map(|d| (d.code, d.span().line, d.span().col))
+            .collect()
+    }
+
+    #[test]
+    fn program_with_decls() {
+        let s = "PROGRAM osszeg\n\
+                 VÁLTOZÓK:\n\
+                 \x20 i, n: EGÉSZ\n\
+                 \x20 s: VALÓS ** the sum\n\
+                 \n\
+                 BE: n\n\
+                 i := 1\n\
+                 CIKLUS AMÍG i <= n\n\
+                 \x20 s := s + i\n\
+                 \x20 i := i + 1\n\
+                 CIKLUS_VÉGE\n\
+                 KI: s\n\
+                 PROGRAM_VÉGE\n";
+        let p = Parser::from(s).parse().unwrap();
+
+        assert_eq!(p.name, "osszeg");
+        assert_eq!(p.decls.type_of("n"), Some(TypeName::Int));
+        assert_eq!(p.decls.type_of("s"), Some(TypeName::Float));
+        assert_eq!(p.body.len(), 4);
+        assert_eq!(p.body[0], Statement::Input(vec![var("n")]));
+        assert_eq!(p.body[1], Statement::Assign(var("i"), int(1)));
+        match &p.body[2] {
+            Statement::While(cond, body) => {
+                assert_eq!(*cond, bin(BinaryOperator::Le, var("i"), var("n")));
+                assert_eq!((cond.span.line, cond.span.col, cond.span.end - cond.span.begin), (8, 13, 6));
+                assert_eq!(body.len(), 2);
+            },
+            s => panic!("expected loop, got {:?}", s)
+        }
+    }
+
+    #[test]
+    fn if_chain() {
+        let s = "PROGRAM p\n\
+                 HA x = 1 AKKOR\n\
+                 \x20 KI: 1\n\
+                 KÜLÖNBEN HA x = 2 AKKOR\n\
+                 \x20 KI: 2\n\
+                 KÜLÖNBEN\n\
+                 \x20 KI: 3\n\
+                 HA_VÉGE\n\
+                 PROGRAM_VÉGE";
+        let p = Parser::from(s).parse().unwrap();
+
+        match &p.body[0] {
+            Statement::If { branches, otherwise } => {
+                assert_eq!(branches.len(), 2);
+                assert_eq!(otherwise, &Some(vec![Statement::Output(vec![int(3)])]));
+            },
+            s => panic!("expected if, got {:?}", s)
+        }
+    }
+
+    #[test]
+    fn subprograms() {
+        let s = "FÜGGVÉNY negyzet(x: EGÉSZ): EGÉSZ\n\
+                 \x20 negyzet := x * x\n\
+                 FÜGGVÉNY_VÉGE\n\
+                 \n\
+                 PROGRAM p\n\
+                 \x20 kiir(negyzet(2), 3)\n\
+                 PROGRAM_VÉGE\n\
+                 \n\
+                 ELJÁRÁS kiir(a, b: EGÉSZ)\n\
+                 \x20 KI: a, b\n\
+                 ELJÁRÁS_VÉGE\n";
+        let p = Parser::from(s).parse().unwrap();
+
+        assert_eq!(p.subprograms.len(), 2);
+        let f = p.subprogram("negyzet").unwrap();
+        assert_eq!(f.result, Some(TypeName::Int));
+        assert_eq!(f.params.len(), 1);
+        assert_eq!((f.params[0].name.as_str(), &f.params[0].ptype), ("x", &TypeName::Int));
+        let e = p.subprogram("kiir").unwrap();
+        assert!(!e.is_function());
+        assert_eq!(e.params.len(), 2);
+
+        let call: Expression = ExprKind::Call(String::from("negyzet"), vec![int(2)]).into();
+        let stmt: Expression = ExprKind::Call(String::from("kiir"), vec![call, int(3)]).into();
+        assert_eq!(p.body[0], Statement::Call(stmt));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(expr("1 + 2 * 3"), bin(BinaryOperator::Plus, int(1),
+            bin(BinaryOperator::Star, int(2), int(3))));
+        assert_eq!(expr("a - b - c"), bin(BinaryOperator::Minus,
+            bin(BinaryOperator::Minus, var("a"), var("b")), var("c")));
+        assert_eq!(expr("a ^ b ^ c"), bin(BinaryOperator::Hat, var("a"),
+            bin(BinaryOperator::Hat, var("b"), var("c"))));
+        assert_eq!(expr("NEM a ÉS b"), bin(BinaryOperator::And,
+            ExprKind::Unary(UnaryOperator::Not, Box::new(var("a"))).into(), var("b")));
+        assert_eq!(expr("t[i] MOD 2"), bin(BinaryOperator::Mod,
+            bin(BinaryOperator::Bracket, var("t"), var("i")), int(2)));
+        assert_eq!(expr("-a ^ b * c"), bin(BinaryOperator::Star,
+            ExprKind::Unary(UnaryOperator::Minus, Box::new(bin(BinaryOperator::Hat, var("a"), var("b")))).into(),
+            var("c")));
+        assert_eq!(expr("a VAGY b ÉS c = d"), bin(BinaryOperator::Or, var("a"),
+            bin(BinaryOperator::And, var("b"), bin(BinaryOperator::Eq, var("c"), var("d")))));
+        assert_eq!(expr("(a)*(b-1)<=-c"), bin(BinaryOperator::Le,
+            bin(BinaryOperator::Star, var("a"), bin(BinaryOperator::Minus, var("b"), int(1))),
+            ExprKind::Unary(UnaryOperator::Minus, Box::new(var("c"))).into()));
+    }
+
+    #[test]
+    fn comparisons_dont_chain() {
+        assert_eq!(errors("PROGRAM p\nHA a < b < c AKKOR\nHA_VÉGE\nPROGRAM_VÉGE"), vec![(Code::Expected, 2, 10)]);
+        assert_eq!(expr("a < b ÉS b < c"), bin(BinaryOperator::And,
+            bin(BinaryOperator::Lt, var("a"), var("b")), bin(BinaryOperator::Lt, var("b"), var("c"))));
+    }
+
+    #[test]
+    fn collects_errors() {
+        assert_eq!(errors("PROGRAM p\nx := \nPROGRAM_VÉGE"), vec![(Code::Expected, 2, 6)]);
+
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 x: EGÉSZ\n\
+                 \x20 y, x: VALÓS\n\
+                 \x20 z EGÉSZ\n\
+                 HA x < AKKOR\n\
+                 \x20 x := 1 +\n\
+                 CIKLUS_VÉGE\n\
+                 HA_VÉGE\n\
+                 x := 2 3\n";
+        assert_eq!(errors(s), vec![
+            (Code::DupName, 4, 6),
+            (Code::DeclColon, 5, 5),
+            (Code::Expected, 6, 8),
+            (Code::Expected, 7, 11),
+            (Code::Unexpected, 8, 1),
+            (Code::Expected, 10, 8),
+            (Code::Expected, 11, 1),
+        ]);
+
+        assert_eq!(errors("PROGRAM p\nx := 1 $ 2\nPROGRAM_VÉGE"), vec![(Code::UnknownOperator, 2, 8)]);
+    }
+
+    #[test]
+    fn dialects() {
+        let en = "PROGRAM p\n\
+                  VARIABLES:\n\
+                  \x20 x: REAL\n\
+                  x := 1.5\n\
+                  IF x > 1 THEN\n\
+                  \x20 OUT: x\n\
+                  ELSE\n\
+                  \x20 LOOP WHILE x < 1\n\
+                  \x20   x := x * 2\n\
+                  \x20 END_LOOP\n\
+                  END_IF\n\
+                  END_PROGRAM\n";
+        let hu = en.replace("VARIABLES", "VÁLTOZÓK").replace("REAL", "VALÓS")
+            .replace("1.5", "1,5").replace("END_IF", "HA_VÉGE").replace("IF", "HA")
+            .replace("THEN", "AKKOR").replace("OUT", "KI")
+            .replace("ELSE", "KÜLÖNBEN").replace("END_LOOP", "CIKLUS_VÉGE")
+            .replace("LOOP WHILE", "CIKLUS AMÍG").replace("END_PROGRAM", "PROGRAM_VÉGE");
+
+        let program = Parser::new(Lexer::with_dialect(en, Dialect::english())).parse().unwrap();
+        assert_eq!(program.body, Parser::from(&hu).parse().unwrap().body);
+        assert_eq!(program.decls.type_of("x"), Some(TypeName::Float));
+
+        let d = Parser::new(Lexer::with_dialect("PROGRAM p\nIF 1 THEN\n", Dialect::english()))
+            .parse().unwrap_err();
+        assert_eq!(d.iter().next().unwrap().args[0], Arg::from("`END_IF`"));
+
+        let d = Dialect::default()
+            .with_operator("<>", BinaryOperator::Ne)
+            .with_operator("&&", BinaryOperator::And);
+        let program = Parser::new(Lexer::with_dialect("PROGRAM p\nl := a <> b && c\nPROGRAM_VÉGE\n", d))
+            .parse().unwrap();
+        assert_eq!(program.body, Parser::from("PROGRAM p\nl := a /= b ÉS c\nPROGRAM_VÉGE\n").parse().unwrap().body);
+    }
+
+    #[test]
+    fn docs() {
+        let s = "*** The exercise.\n\
+                 PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 *** counted so far\n\
+                 \x20 *** from zero\n\
+                 \x20 n: EGÉSZ *** trailing\n\
+                 \x20 x: VALÓS\n\
+                 \x20 ** plain\n\
+                 \x20 y: VALÓS\n\
+                 \x20 *** far away\n\
+                 \n\
+                 \x20 z: VALÓS\n\
+                 PROGRAM_VÉGE\n\
+                 (**\n\
+                 \x20 Square of `x`.\n\
+                 *)\n\
+                 FÜGGVÉNY sq(x: VALÓS): VALÓS\n\
+                 VÁLTOZÓK:\n\
+                 \x20 ***** not a doc\n\
+                 \x20 v: VALÓS\n\
+                 FÜGGVÉNY_VÉGE\n";
+        let program = Parser::from(s).parse().unwrap();
+        assert_eq!(program.doc.as_deref(), Some("The exercise."));
+        let docs: Vec<Option<&str>> = program.decls.variables.iter().map(|d| d.doc.as_deref()).collect();
+        assert_eq!(docs, [Some("counted so far\nfrom zero"), None, None, None]);
+
+        let sq = program.subprogram("sq").unwrap();
+        assert_eq!(sq.doc.as_deref(), Some("Square of `x`."));
+        assert_eq!(sq.decls.variables[0].doc, None);
+    }
+
+    #[test]
+    fn arrays() {
+        let s = "PROGRAM p\n\
+                 VÁLTOZÓK:\n\
+                 \x20 t: TÖMB[10: EGÉSZ]\n\
+                 t[1] := |t|\n\
+                 PROGRAM_VÉGE\n\
+                 FÜGGVÉNY f(a: TÖMB[3: VALÓS]): TÖMB[3: VALÓS]\n\
+                 \x20 f := a\n\
+                 FÜGGVÉNY_VÉGE\n";
+        let program = Parser::from(s).parse().unwrap();
+        let t = TypeName::Array(Box::new(TypeName::Int), 10);
+        assert_eq!(program.decls.type_of("t"), Some(t.clone()));
+        assert_eq!(t.name(), "TÖMB[10: EGÉSZ]");
+
+        let f = program.subprogram("f").unwrap();
+        assert_eq!(f.params[0].ptype, TypeName::Array(Box::new(TypeName::Float), 3));
+        assert_eq!(f.result, Some(f.params[0].ptype.clone()));
+
+        let decl = |t: &str| format!("PROGRAM p\nVÁLTOZÓK:\nt: {}\nPROGRAM_VÉGE", t);
+        assert_eq!(errors(&decl("TÖMB[0: EGÉSZ]")), vec![(Code::ArraySize, 3, 9)]);
+        assert_eq!(errors(&decl("TÖMB[n: EGÉSZ]")), vec![(Code::ArraySize, 3, 9)]);
+        assert_eq!(errors(&decl("TÖMB[5 EGÉSZ]")), vec![(Code::Expected, 3, 11)]);
+        assert_eq!(errors(&decl("TÖMB[5: ]")), vec![(Code::DeclType, 3, 12)]);
+    }
+
+    #[test]
+    fn param_modes() {
+        let s = "PROGRAM p\nPROGRAM_VÉGE\n\
+                 ELJÁRÁS f(a: EGÉSZ, KI b, c: VALÓS, CÍM t: TÖMB[3: EGÉSZ])\n\
+                 ELJÁRÁS_VÉGE";
+        let program = Parser::from(s).parse().unwrap();
+        let modes: Vec<(&str, ParamMode)> = program.subprogram("f").unwrap().params.iter()
+            .map(|p| (p.name.as_str(), p.mode))
+            .collect();
+        assert_eq!(modes, [("a", ParamMode::In), ("b", ParamMode::Out), ("c", ParamMode::Out), ("t", ParamMode::Ref)]);
+    }
+
+    #[test]
+    fn matrices() {
+        let s = "PROGRAM p\nVÁLTOZÓK:\nm: TÖMB[2, 3: VALÓS]\nPROGRAM_VÉGE";
+        let m = Parser::from(s).parse().unwrap().decls.type_of("m").unwrap();
+        let row = TypeName::Array(Box::new(TypeName::Float), 3);
+        assert_eq!(m, TypeName::Array(Box::new(row), 2));
+        assert_eq!(m.name(), "TÖMB[2, 3: VALÓS]");
+
+        assert_eq!(expr("m[i, j + 1]"), expr("m[i][j + 1]"));
+        assert_eq!(expr("m[i, j]").render(), "m[i][j]");
+        let s = "PROGRAM p\nVÁLTOZÓK:\nm: TÖMB[2, 0: VALÓS]\nPROGRAM_VÉGE";
+        assert_eq!(errors(s), vec![(Code::ArraySize, 3, 12)]);
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::iter::Peekable;
+use super::tokenizer::Span;
+
+/// Encodings of source files. Old exercises are often saved in one of
+/// the Central European 8 bit ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// ISO-8859-2
+    Latin2,
+    /// Windows-1250
+    Cp1250
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Characters of the bytes from 0xA0 up in ISO-8859-2, below that it's
+/// ASCII and control characters.
+const LATIN2: [char; 96] = [
+    '\u{A0}', '\u{104}', '\u{2D8}', '\u{141}', '\u{A4}', '\u{13D}', '\u{15A}', '\u{A7}',
+    '\u{A8}', '\u{160}', '\u{15E}', '\u{164}', '\u{179}', '\u{AD}', '\u{17D}', '\u{17B}',
+    '\u{B0}', '\u{105}', '\u{2DB}', '\u{142}', '\u{B4}', '\u{13E}', '\u{15B}', '\u{2C7}',
+    '\u{B8}', '\u{161}', '\u{15F}', '\u{165}', '\u{17A}', '\u{2DD}', '\u{17E}', '\u{17C}',
+    '\u{154}', '\u{C1}', '\u{C2}', '\u{102}', '\u{C4}', '\u{139}', '\u{106}', '\u{C7}',
+    '\u{10C}', '\u{C9}', '\u{118}', '\u{CB}', '\u{11A}', '\u{CD}', '\u{CE}', '\u{10E}',
+    '\u{110}', '\u{143}', '\u{147}', '\u{D3}', '\u{D4}', '\u{150}', '\u{D6}', '\u{D7}',
+    '\u{158}', '\u{16E}', '\u{DA}', '\u{170}', '\u{DC}', '\u{DD}', '\u{162}', '\u{DF}',
+    '\u{155}', '\u{E1}', '\u{E2}', '\u{103}', '\u{E4}', '\u{13A}', '\u{107}', '\u{E7}',
+    '\u{10D}', '\u{E9}', '\u{119}', '\u{EB}', '\u{11B}', '\u{ED}', '\u{EE}', '\u{10F}',
+    '\u{111}', '\u{144}', '\u{148}', '\u{F3}', '\u{F4}', '\u{151}', '\u{F6}', '\u{F7}',
+    '\u{159}', '\u{16F}', '\u{FA}', '\u{171}', '\u{FC}', '\u{FD}', '\u{163}', '\u{2D9}',
+];
+
+/// Characters of the bytes from 0x80 up in Windows-1250. The five
+/// unused bytes are decoded as U+FFFD.
+const CP1250: [char; 128] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{FFFD}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{FFFD}', '\u{2030}', '\u{160}', '\u{2039}', '\u{15A}', '\u{164}', '\u{17D}', '\u{179}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{FFFD}', '\u{2122}', '\u{161}', '\u{203A}', '\u{15B}', '\u{165}', '\u{17E}', '\u{17A}',
+    '\u{A0}', '\u{2C7}', '\u{2D8}', '\u{141}', '\u{A4}', '\u{104}', '\u{A6}', '\u{A7}',
+    '\u{A8}', '\u{A9}', '\u{15E}', '\u{AB}', '\u{AC}', '\u{AD}', '\u{AE}', '\u{17B}',
+    '\u{B0}', '\u{B1}', '\u{2DB}', '\u{142}', '\u{B4}', '\u{B5}', '\u{B6}', '\u{B7}',
+    '\u{B8}', '\u{105}', '\u{15F}', '\u{BB}', '\u{13D}', '\u{2DD}', '\u{13E}', '\u{17C}',
+    '\u{154}', '\u{C1}', '\u{C2}', '\u{102}', '\u{C4}', '\u{139}', '\u{106}', '\u{C7}',
+    '\u{10C}', '\u{C9}', '\u{118}', '\u{CB}', '\u{11A}', '\u{CD}', '\u{CE}', '\u{10E}',
+    '\u{110}', '\u{143}', '\u{147}', '\u{D3}', '\u{D4}', '\u{150}', '\u{D6}', '\u{D7}',
+    '\u{158}', '\u{16E}', '\u{DA}', '\u{170}', '\u{DC}', '\u{DD}', '\u{162}', '\u{DF}',
+    '\u{155}', '\u{E1}', '\u{E2}', '\u{103}', '\u{E4}', '\u{13A}', '\u{107}', '\u{E7}',
+    '\u{10D}', '\u{E9}', '\u{119}', '\u{EB}', '\u{11B}', '\u{ED}', '\u{EE}', '\u{10F}',
+    '\u{111}', '\u{144}', '\u{148}', '\u{F3}', '\u{F4}', '\u{151}', '\u{F6}', '\u{F7}',
+    '\u{159}', '\u{16F}', '\u{FA}', '\u{171}', '\u{FC}', '\u{FD}', '\u{163}', '\u{2D9}',
+];
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin2" | "latin-2" | "iso-8859-2" => Some(Encoding::Latin2),
+            "cp1250" | "windows-1250" => Some(Encoding::Cp1250),
+            _ => None
+        }
+    }
+
+    /// Guesses the encoding of a file without a BOM. Anything that is
+    /// valid UTF-8 is taken as such. Bytes 0x80-0x9F are control
+    /// characters in ISO-8859-2, so they mean Windows-1250; otherwise
+    /// the two agree on the Hungarian letters, and ISO-8859-2 is chosen.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        if std::str::from_utf8(bytes).is_ok() {
+            Encoding::Utf8
+        } else if bytes.iter().any(|b| (0x80..0xA0).contains(b)) {
+            Encoding::Cp1250
+        } else {
+            Encoding::Latin2
+        }
+    }
+
+    fn decode_byte(&self, b: u8) -> char {
+        match self {
+            _ if b < 0x80 => b as char,
+            Encoding::Cp1250 => CP1250[b as usize - 0x80],
+            _ if b < 0xA0 => b as char,
+            _ => LATIN2[b as usize - 0xA0]
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Latin2 => "ISO-8859-2",
+            Encoding::Cp1250 => "Windows-1250"
+        })
+    }
+}
+
+/// A file that isn't in the encoding it was said to be in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub encoding: Encoding,
+    /// Where the first invalid byte is
+    pub offset: usize
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not valid {} at byte {}", self.encoding, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Program text decoded from a file: UTF-8 without a BOM, and with every
+/// line ending a `\n`. Spans of the text can be mapped back to byte
+/// offsets in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    text: String,
+    encoding: Encoding,
+
+    /// Offsets in `text` and the file where the difference between the
+    /// two changes, in order. Between them they go byte for byte.
+    offsets: Vec<(usize, usize)>
+}
+
+impl Source {
+    /// Decodes `bytes` in `encoding`, or the one they seem to be in if
+    /// it's `None`. A UTF-8 BOM is stripped, and it also settles the
+    /// encoding if none is given.
+    pub fn decode(bytes: &[u8], encoding: Option<Encoding>) -> Result<Source, DecodeError> {
+        let rest = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        let bom = bytes.len() - rest.len();
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None if bom > 0 => Encoding::Utf8,
+            None => Encoding::detect(rest)
+        };
+
+        let chars: Box<dyn Iterator<Item = (usize, char)>> = match encoding {
+            Encoding::Utf8 => {
+                let s = std::str::from_utf8(rest)
+                    .map_err(|e| DecodeError { encoding, offset: bom + e.valid_up_to() })?;
+                Box::new(s.chars().map(|c| (c.len_utf8(), c)))
+            },
+            _ => Box::new(rest.iter().map(move |&b| (1, encoding.decode_byte(b))))
+        };
+
+        let mut source = Source { text: String::with_capacity(rest.len()), encoding, offsets: vec![(0, bom)] };
+        source.push_all(chars.peekable(), bom);
+        Ok(source)
+    }
+
+    /// Appends characters given with their length in the file, which
+    /// starts at `original`.
+    fn push_all<I>(&mut self, mut chars: Peekable<I>, mut original: usize)
+        where I: Iterator<Item = (usize, char)>
+    {
+        while let Some((mut len, c)) = chars.next() {
+            let before = self.text.len();
+            match c {
+                // `\r\n` is one `\n`, starting where the `\r` is
+                '\r' => {
+                    if let Some((n, _)) = chars.next_if(|&(_, next)| next == '\n') {
+                        len += n;
+                    }
+                    self.text.push('\n');
+                },
+                _ => self.text.push(c)
+            }
+            original += len;
+            if self.text.len() - before != len {
+                self.offsets.push((self.text.len(), original));
+            }
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Offset in the file of the character at `offset` in the text.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let i = self.offsets.partition_point(|&(decoded, _)| decoded <= offset) - 1;
+        let (decoded, original) = self.offsets[i];
+        let result = original + (offset - decoded);
+        // inside a character that got longer, there's no byte of its own
+        match self.offsets.get(i + 1) {
+            Some(&(_, next)) => result.min(next),
+            None => result
+        }
+    }
+
+    /// `span` with its offsets in the file instead of the text. Lines and
+    /// columns are the same in both.
+    pub fn original_span(&self, span: Span) -> Span {
+        Span { begin: self.original_offset(span.begin), end: self.original_offset(span.end), ..span }
+    }
+}
+
+/// Text that is already a string, only the line endings are normalized.
+impl From<&str> for Source {
+    fn from(s: &str) -> Source {
+        let mut source = Source { text: String::with_capacity(s.len()), encoding: Encoding::Utf8, offsets: vec![(0, 0)] };
+        source.push_all(s.chars().map(|c| (c.len_utf8(), c)).peekable(), 0);
+        source
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        // "őz árvíztűrő" in each
+        let latin2 = b"\xF5z \xE1rv\xEDzt\xFBr\xF5";
+        assert_eq!(Encoding::detect(latin2), Encoding::Latin2);
+        assert_eq!(Source::decode(latin2, None).unwrap().text(), "őz árvíztűrő");
+
+        let cp1250 = b"\x9Aa \xF5";
+        assert_eq!(Encoding::detect(cp1250), Encoding::Cp1250);
+        assert_eq!(Source::decode(cp1250, None).unwrap().text(), "ša ő");
+        assert_eq!(Source::decode(cp1250, Some(Encoding::Latin2)).unwrap().text(), "\u{9A}a ő");
+
+        let utf8 = "\u{FEFF}őz".as_bytes();
+        let source = Source::decode(utf8, None).unwrap();
+        assert_eq!((source.text(), source.encoding()), ("őz", Encoding::Utf8));
+
+        let err = Source::decode(latin2, Some(Encoding::Utf8)).unwrap_err();
+        assert_eq!(err.offset, 0);
+        assert_eq!(Encoding::from_name("Windows-1250"), Some(Encoding::Cp1250));
+    }
+
+    #[test]
+    fn offsets() {
+        let source = Source::decode(b"a\r\n\xF5\rb\r\n", Some(Encoding::Latin2)).unwrap();
+        assert_eq!(source.text(), "a\nő\nb\n");
+        let offsets: Vec<usize> = [0, 1, 2, 4, 5, 6, 7].iter()
+            .map(|&i| source.original_offset(i))
+            .collect();
+        assert_eq!(offsets, [0, 1, 3, 4, 5, 6, 8]);
+        let span = Span { begin: 2, end: 4, line: 2, col: 1 };
+        assert_eq!(source.original_span(span), Span { begin: 3, end: 4, line: 2, col: 1 });
+
+        let source = Source::decode("\u{FEFF}a\r\nb".as_bytes(), None).unwrap();
+        let span = Span { begin: 2, end: 3, line: 2, col: 1 };
+        assert_eq!(source.original_span(span), Span { begin: 6, end: 7, line: 2, col: 1 });
+        assert_eq!(Source::from("a\r\nb\rc").text(), "a\nb\nc");
+    }
+}
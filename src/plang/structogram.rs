@@ -0,0 +1,277 @@
+use super::ast::*;
+
+// Sizes in pixels. Text is measured by character count, which is exact
+// enough with a monospace font.
+const FONT_SIZE: usize = 14;
+const CHAR_WIDTH: usize = 9;
+const LINE_HEIGHT: usize = 28;
+const PADDING: usize = 8;
+/// Width of the bar on the left of a loop
+const INDENT: usize = 24;
+/// Distance of the extra lines marking a procedure call
+const CALL_MARGIN: usize = 6;
+const EMPTY_WIDTH: usize = 40;
+const MARGIN: usize = 16;
+
+/// A statement as it is drawn. `KÜLÖNBEN HA` chains are broken up into
+/// branches nested in the `KÜLÖNBEN` side.
+#[derive(Debug, PartialEq)]
+enum Node {
+    Simple(String),
+    Call(String),
+    Loop(String, Vec<Node>),
+    Branch(String, Vec<Node>, Vec<Node>)
+}
+
+fn nodes(block: &[Statement]) -> Vec<Node> {
+    block.iter().map(node).collect()
+}
+
+fn node(stmt: &Statement) -> Node {
+    match stmt {
+        Statement::Assign(target, value) => Node::Simple(format!("{} := {}", target.render(), value.render())),
+        Statement::If { branches, otherwise } => branch(branches, otherwise),
+        Statement::While(cond, body) => Node::Loop(cond.render(), nodes(body)),
+        Statement::Call(call) => Node::Call(call.render()),
+        Statement::Output(args) => Node::Simple(io_text("KI", args)),
+        Statement::Input(args) => Node::Simple(io_text("BE", args))
+    }
+}
+
+fn branch(branches: &[(Expression, Block)], otherwise: &Option<Block>) -> Node {
+    let (cond, body) = &branches[0];
+    let other = if branches.len() > 1 {
+        vec![branch(&branches[1..], otherwise)]
+    } else {
+        otherwise.as_ref().map_or(vec![], |b| nodes(b))
+    };
+    Node::Branch(cond.render(), nodes(body), other)
+}
+
+fn io_text(word: &str, args: &[Expression]) -> String {
+    let args: Vec<String> = args.iter().map(|a| a.render()).collect();
+    format!("{}: {}", word, args.join(", "))
+}
+
+/// Width of already escaped text, counting each entity as one character.
+fn text_width(s: &str) -> usize {
+    let mut count = 0;
+    let mut in_entity = false;
+    for c in s.chars() {
+        match c {
+            '&' => in_entity = true,
+            ';' if in_entity => {
+                in_entity = false;
+                count += 1;
+            },
+            _ if in_entity => (),
+            _ => count += 1
+        }
+    }
+    count * CHAR_WIDTH
+}
+
+impl Node {
+    fn min_width(&self) -> usize {
+        match self {
+            Node::Simple(text) => text_width(text) + 2 * PADDING,
+            Node::Call(text) => text_width(text) + 2 * (PADDING + CALL_MARGIN),
+            Node::Loop(cond, body) => (text_width(cond) + 2 * PADDING).max(INDENT + seq_min_width(body)),
+            Node::Branch(cond, yes, no) => {
+                let (yes, no) = Node::columns(cond, yes, no);
+                yes + no
+            }
+        }
+    }
+
+    /// Minimal widths of the two sides of a branch. The condition is
+    /// centered above where they meet, so half of it has to fit on each.
+    fn columns(cond: &str, yes: &[Node], no: &[Node]) -> (usize, usize) {
+        let half = text_width(cond) / 2 + 2 * PADDING;
+        (seq_min_width(yes).max(half), seq_min_width(no).max(half))
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Node::Simple(_) | Node::Call(_) => LINE_HEIGHT,
+            Node::Loop(_, body) => LINE_HEIGHT + seq_height(body),
+            Node::Branch(_, yes, no) => 2 * LINE_HEIGHT + seq_height(yes).max(seq_height(no))
+        }
+    }
+
+    fn draw(&self, svg: &mut Svg, x: usize, y: usize, w: usize) {
+        let h = self.height();
+        match self {
+            Node::Simple(text) => {
+                svg.rect(x, y, w, h);
+                svg.text(x + PADDING, y + LINE_HEIGHT / 2, "start", text);
+            },
+            Node::Call(text) => {
+                svg.rect(x, y, w, h);
+                svg.line(x + CALL_MARGIN, y, x + CALL_MARGIN, y + h);
+                svg.line(x + w - CALL_MARGIN, y, x + w - CALL_MARGIN, y + h);
+                svg.text(x + CALL_MARGIN + PADDING, y + LINE_HEIGHT / 2, "start", text);
+            },
+            Node::Loop(cond, body) => {
+                svg.rect(x, y, w, h);
+                svg.text(x + PADDING, y + LINE_HEIGHT / 2, "start", cond);
+                draw_seq(svg, body, x + INDENT, y + LINE_HEIGHT, w - INDENT, h - LINE_HEIGHT);
+            },
+            Node::Branch(cond, yes, no) => {
+                // the extra width is shared by the two sides equally
+                let (min_yes, min_no) = Node::columns(cond, yes, no);
+                let yes_w = min_yes + (w - min_yes - min_no) / 2;
+                let split = x + yes_w;
+                let header = 2 * LINE_HEIGHT;
+
+                svg.rect(x, y, w, header);
+                svg.line(x, y, split, y + header);
+                svg.line(x + w, y, split, y + header);
+                svg.text(split, y + LINE_HEIGHT / 2, "middle", cond);
+                svg.text(x + PADDING, y + header - LINE_HEIGHT / 2, "start", "I");
+                svg.text(x + w - PADDING, y + header - LINE_HEIGHT / 2, "end", "H");
+
+                draw_seq(svg, yes, x, y + header, yes_w, h - header);
+                draw_seq(svg, no, split, y + header, w - yes_w, h - header);
+            }
+        }
+    }
+}
+
+fn seq_min_width(seq: &[Node]) -> usize {
+    seq.iter().map(|n| n.min_width()).fold(EMPTY_WIDTH, usize::max)
+}
+
+fn seq_height(seq: &[Node]) -> usize {
+    match seq.iter().map(|n| n.height()).sum() {
+        0 => LINE_HEIGHT,
+        h => h
+    }
+}
+
+/// Draws a sequence of statements in the given area. If it's taller than
+/// the statements, there's an empty space left at the bottom.
+fn draw_seq(svg: &mut Svg, seq: &[Node], x: usize, y: usize, w: usize, h: usize) {
+    svg.rect(x, y, w, h);
+    let mut top = y;
+    for n in seq {
+        n.draw(svg, x, top, w);
+        top += n.height();
+    }
+}
+
+struct Svg {
+    body: String
+}
+
+impl Svg {
+    fn rect(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        self.body += &format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n", x, y, w, h);
+    }
+
+    fn line(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        self.body += &format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n", x1, y1, x2, y2);
+    }
+
+    /// `text` must already be escaped.
+    fn text(&mut self, x: usize, y: usize, anchor: &str, text: &str) {
+        self.body += &format!("<text x=\"{}\" y=\"{}\" text-anchor=\"{}\">{}</text>\n", x, y, anchor, text);
+    }
+}
+
+fn title(sub: &Subprogram) -> String {
+    let params: Vec<String> = sub.params.iter()
+        .map(|p| match p.mode.keyword() {
+            Some(mode) => format!("{} {}: {}", mode, escape_html(&p.name), p.ptype.name()),
+            None => format!("{}: {}", escape_html(&p.name), p.ptype.name())
+        })
+        .collect();
+    let mut s = format!("{}({})", escape_html(&sub.name), params.join(", "));
+    if let Some(t) = &sub.result {
+        s += ": ";
+        s += &t.name();
+    }
+    s
+}
+
+/// Draws a structogram of the program and one of each subprogram under
+/// it, as an SVG image.
+pub fn render_svg(program: &Program) -> String {
+    let mut diagrams = vec![(escape_html(&program.name), nodes(&program.body))];
+    for sub in &program.subprograms {
+        diagrams.push((title(sub), nodes(&sub.body)));
+    }
+
+    let width = diagrams.iter()
+        .map(|(t, seq)| seq_min_width(seq).max(text_width(t)))
+        .max()
+        .unwrap_or(EMPTY_WIDTH);
+
+    let mut svg = Svg { body: String::new() };
+    let mut y = MARGIN;
+    for (t, seq) in &diagrams {
+        svg.text(MARGIN, y + LINE_HEIGHT / 2, "start", t);
+        y += LINE_HEIGHT;
+        let h = seq_height(seq);
+        draw_seq(&mut svg, seq, MARGIN, y, width, h);
+        y += h + MARGIN;
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <style>\n\
+         rect, line {{ fill: none; stroke: black; }}\n\
+         text {{ font-family: monospace; font-size: {f}px; dominant-baseline: middle; }}\n\
+         </style>\n{}</svg>\n",
+        svg.body,
+        w = width + 2 * MARGIN,
+        h = y,
+        f = FONT_SIZE
+    )
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::Parser;
+
+    fn program(body: &str) -> Program {
+        let src = format!("PROGRAM p\n{}\nPROGRAM_VÉGE\n", body);
+        Parser::from(&src).parse().unwrap()
+    }
+
+    #[test]
+    fn else_if_nests() {
+        let p = program("HA a < 1 AKKOR\nx := 1\nKÜLÖNBEN HA a < 2 AKKOR\nx := 2\nHA_VÉGE");
+        let expect = Node::Branch(String::from("a &lt; 1"),
+            vec![Node::Simple(String::from("x := 1"))],
+            vec![Node::Branch(String::from("a &lt; 2"), vec![Node::Simple(String::from("x := 2"))], vec![])]);
+        assert_eq!(nodes(&p.body), vec![expect]);
+    }
+
+    #[test]
+    fn sizes() {
+        let p = program("CIKLUS AMÍG i < n\ni := i + 1\nCIKLUS_VÉGE");
+        let seq = nodes(&p.body);
+        assert_eq!(text_width("i &lt; n"), 5 * CHAR_WIDTH);
+        assert_eq!(seq[0].height(), 2 * LINE_HEIGHT);
+        assert_eq!(seq_min_width(&seq), INDENT + 10 * CHAR_WIDTH + 2 * PADDING);
+
+        // an empty side still takes up a line
+        let p = program("HA a AKKOR\nKI: a\nHA_VÉGE");
+        let seq = nodes(&p.body);
+        assert_eq!(seq[0].height(), 3 * LINE_HEIGHT);
+        assert_eq!(seq[0].min_width(), (5 * CHAR_WIDTH + 2 * PADDING) + EMPTY_WIDTH);
+    }
+
+    #[test]
+    fn svg() {
+        let p = program("HA a AKKOR\nKI: a\nHA_VÉGE\nf(a)");
+        let svg = render_svg(&p);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("<line ").count(), 4);
+        assert!(svg.contains(">KI: a</text>"));
+        assert!(svg.contains(">f(a)</text>"));
+    }
+}
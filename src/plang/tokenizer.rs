@@ -1,3 +1,12 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use super::diagnostic::{ Diagnostic, Code, Message };
+use super::catalog::Phrase;
+use super::unicode::{ is_combining_mark, nfc };
+use super::dialect::normalize;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     Undefined,
@@ -15,24 +24,80 @@ pub enum TokenType {
     EOF
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Location of a token in the source text. `begin` and `end` are byte
+/// offsets, `line` and `col` are 1-based and refer to `begin`, columns 
+/// counted in characters.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub begin: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize
+}
+
+impl Span {
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        if other.begin < self.begin {
+            return other.to(*self);
+        }
+        Span { end: self.end.max(other.end), ..*self }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Which decimal separator numbers are written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberPolicy {
+    /// `3.14`, a comma always separates list items.
+    Dot,
+    /// `3,14`, so `f(1,2)` has a single argument and lists need a space
+    /// after their commas.
+    Comma,
+    /// Both `3.14` and `3,14`, a comma counting as the decimal separator
+    /// only outside argument lists and index brackets, where it can't be
+    /// a list separator.
+    #[default]
+    Hungarian
+}
+
+/// Tokens compare equal by content, their position is not taken into 
+/// account.
+#[derive(Debug, Clone)]
 pub struct Token<'a> {
     pub sval: &'a str,
     pub nval: f64,
-    pub ttype: TokenType
+    pub ttype: TokenType,
+    pub span: Span,
+    /// Content of a string or character literal with its escape 
+    /// sequences decoded. `None` for every other token.
+    pub value: Option<String>,
+    /// The character of a valid character literal.
+    pub cval: Option<char>
+}
+
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sval == other.sval && self.nval == other.nval && self.ttype == other.ttype
+    }
 }
 
 impl Token<'_> {
-    pub fn from(sval: &str, nval: f64, ttype: TokenType) -> Token {
-        Token { sval, nval, ttype }
+    pub fn from(sval: &str, nval: f64, ttype: TokenType) -> Token<'_> {
+        Token { sval, nval, ttype, span: Span::default(), value: None, cval: None }
     }
 
-    pub fn from_str(sval: &str, ttype: TokenType) -> Token {
-        Token { sval, nval: 0.0, ttype }
+    pub fn from_str(sval: &str, ttype: TokenType) -> Token<'_> {
+        Token { sval, nval: 0.0, ttype, span: Span::default(), value: None, cval: None }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tokenizer<'a> {
     cursor: usize,
     text: &'a str,
@@ -46,7 +111,30 @@ pub struct Tokenizer<'a> {
     /// Default value is `false`.
     concat_special: bool,
 
-    comment_defs: Vec<(&'static str, &'static str)>,
+    /// Openings and endings of comments, an empty ending meaning the
+    /// end of the line.
+    comment_defs: Vec<(String, String)>,
+
+    /// With `concat_special`, runs of special characters are split into
+    /// the longest of these (or of the comment openings) they start with.
+    /// Empty means no splitting.
+    operators: Vec<&'static str>,
+    /// Keywords in their normalized form. A parenthesis after one of them
+    /// is for grouping, not an argument list.
+    keywords: Vec<String>,
+
+    numbers: NumberPolicy,
+    /// Parentheses and brackets open at the cursor, innermost last, each
+    /// telling whether it holds a list: arguments or indices.
+    brackets: Vec<bool>,
+
+    /// Last position `locate` was asked about, with its line number and
+    /// the offset where that line starts.
+    loc_offset: usize,
+    loc_line: usize,
+    loc_line_start: usize,
+
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -60,14 +148,17 @@ impl<'a> Tokenizer<'a> {
 
     pub fn with_options(s: &'a str, options: i32) -> Tokenizer<'a>
     {
-        Tokenizer::with_comments(s, options, vec![])
+        Tokenizer::with_comments(s, options, Vec::<(String, String)>::new())
     }
 
-    pub fn with_comments(
+    pub fn with_comments<S: Into<String>>(
         s: &'a str, 
         options: i32, 
-        comment_defs: Vec<(&'static str, &'static str)>) -> Tokenizer<'a>
+        comment_defs: Vec<(S, S)>) -> Tokenizer<'a>
     {
+        let comment_defs = comment_defs.into_iter()
+            .map(|(open, close)| (open.into(), close.into()))
+            .collect();
         let eol_matters = (options & Self::EOL_MATTERS) != 0;
         let concat_special = (options & Self::CONCAT_SPECIAL) != 0;   
 
@@ -76,7 +167,338 @@ impl<'a> Tokenizer<'a> {
             text: s, 
             eol_matters, 
             concat_special,
-            comment_defs
+            comment_defs,
+            operators: Vec::new(),
+            keywords: Vec::new(),
+            numbers: NumberPolicy::default(),
+            brackets: Vec::new(),
+            loc_offset: 0,
+            loc_line: 1,
+            loc_line_start: 0,
+            diagnostics: Vec::new()
+        }
+    }
+
+    pub fn set_operators(&mut self, operators: Vec<&'static str>) {
+        self.operators = operators;
+    }
+
+    pub fn set_keywords(&mut self, keywords: Vec<String>) {
+        self.keywords = keywords;
+    }
+
+    pub fn set_number_policy(&mut self, numbers: NumberPolicy) {
+        self.numbers = numbers;
+    }
+
+    /// Line and column of a byte offset. Offsets should be asked for in 
+    /// increasing order, so the text is scanned only once. `\r\n` counts as 
+    /// a single line break, as does a lone `\r` or `\n`.
+    fn locate(&mut self, offset: usize) -> (usize, usize) {
+        if offset < self.loc_offset {
+            // asked out of order after all, start over
+            self.loc_offset = 0;
+            self.loc_line = 1;
+            self.loc_line_start = 0;
+        }
+
+        let mut prev = if self.loc_offset > 0 {
+            self.text[..self.loc_offset].chars().next_back()
+        } else {
+            None
+        };
+
+        for (i, c) in self.text[self.loc_offset..offset].char_indices() {
+            let at = self.loc_offset + i;
+            match c {
+                '\n' if prev == Some('\r') => self.loc_line_start = at + 1,
+                '\r' | '\n' => {
+                    self.loc_line += 1;
+                    self.loc_line_start = at + 1;
+                },
+                _ => ()
+            }
+            prev = Some(c);
+        }
+        self.loc_offset = offset;
+
+        let col = self.text[self.loc_line_start..offset].chars().count() + 1;
+        (self.loc_line, col)
+    }
+
+    fn span(&mut self, begin: usize, end: usize) -> Span {
+        let (line, col) = self.locate(begin);
+        Span { begin, end, line, col }
+    }
+
+    /// Where the text ends, to point at when something is missing.
+    pub fn end_span(&mut self) -> Span {
+        let end = self.text.len();
+        self.span(end, end)
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Problems found in the tokens returned so far. Each of them is 
+    /// also returned as an `Undefined` token.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Whether `c` followed by a digit at the cursor is a decimal separator.
+    fn is_decimal_separator(&self, c: char) -> bool {
+        match (c, self.numbers) {
+            ('.', NumberPolicy::Dot) | ('.', NumberPolicy::Hungarian) => true,
+            (',', NumberPolicy::Comma) => true,
+            (',', NumberPolicy::Hungarian) => self.brackets.last() != Some(&true),
+            _ => false
+        }
+    }
+
+    /// Whether a parenthesis at `at` holds arguments, that is it follows
+    /// a name or an index, like `f(` or `t[1](`, and isn't for grouping
+    /// as in `HA (`.
+    fn is_call(&self, at: usize) -> bool {
+        let before = self.text[..at].trim_end();
+        let rest = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || is_combining_mark(c));
+        let word = &before[rest.len()..];
+        match word.chars().next() {
+            // a number is not a name
+            Some(c) => !c.is_numeric() && !self.keywords.contains(&normalize(word)),
+            None => before.ends_with(']')
+        }
+    }
+
+    /// Reads a number starting at `tok_begin`: digits, a fraction and an
+    /// exponent like `1.5e3`, the latter two being optional. Letters or a
+    /// second fraction stuck to it make the whole thing a malformed number,
+    /// and so do digits other than 0-9, like `٣٤`.
+    fn number(&mut self, tok_begin: usize) -> Token<'a> {
+        let text = self.text;
+        let char_at = |at: usize| text[at..].chars().next();
+        let digit_at = |at: usize| char_at(at).is_some_and(|c| c.is_ascii_digit());
+        let digits_end = |from: usize| text[from..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(text.len(), |i| from + i);
+
+        let mut end = digits_end(tok_begin);
+        let mut float = false;
+        let mut problem: Option<Message> = None;
+        if end == tok_begin {
+            problem = Some(Phrase::AsciiDigits.into());
+        }
+
+        if let Some(c) = char_at(end) {
+            if self.is_decimal_separator(c) && digit_at(end + 1) {
+                float = true;
+                end = digits_end(end + 1);
+            }
+        }
+
+        if let Some('e') | Some('E') = char_at(end) {
+            let mut at = end + 1;
+            if let Some('+') | Some('-') = char_at(at) {
+                at += 1;
+            }
+            float = true;
+            if digit_at(at) {
+                end = digits_end(at);
+            } else {
+                problem = Some(Phrase::ExponentDigits.into());
+                end = at;
+            }
+        }
+
+        // whatever is stuck to the number belongs to the error
+        let tail = end;
+        loop {
+            match char_at(end) {
+                Some(c) if c.is_alphanumeric() || c == '_' => end += c.len_utf8(),
+                Some(c) if (c == '.' || self.is_decimal_separator(c)) && digit_at(end + 1) => end += 1,
+                _ => break
+            }
+        }
+        if end > tail && problem.is_none() {
+            problem = Some(match char_at(tail) {
+                Some('.') | Some(',') if float => Phrase::ExtraSeparator.into(),
+                // only a dot can get here without a fraction, when it's not
+                // the separator
+                Some('.') => Message::new(Phrase::DecimalSeparator, "`,`"),
+                _ => Phrase::NumberSuffix.into()
+            });
+        }
+
+        self.cursor = end;
+        let sval = &text[tok_begin..end];
+        let mut nval = 0.0;
+        if problem.is_none() {
+            // only ASCII digits got here, so the value is out of range if
+            // it can't be parsed
+            nval = if float {
+                sval.replace(',', ".").parse::<f64>().unwrap_or(f64::INFINITY)
+            } else {
+                sval.parse::<i64>().map_or(f64::INFINITY, |i| i as f64)
+            };
+            if nval.is_infinite() {
+                problem = Some(Phrase::NumberTooLarge.into());
+                nval = 0.0;
+            }
+        }
+
+        let span = self.span(tok_begin, end);
+        let ttype = if problem.is_some() {
+            let mut d = Diagnostic::error(Code::InvalidNumber, span, &[sval]);
+            if let Some(label) = problem {
+                d = d.with_label(label);
+            }
+            self.diagnostics.push(d);
+            TokenType::Undefined
+        } else if float {
+            TokenType::Float
+        } else {
+            TokenType::Int
+        };
+        Token { sval, nval, ttype, span, value: None, cval: None }
+    }
+
+    /// Length of the operator or comment opening `run` starts with, the
+    /// longest one if there are more. A character that starts none of
+    /// them is a token on its own.
+    fn munch(&self, run: &str) -> usize {
+        self.operators.iter()
+            .copied()
+            .chain(self.comment_defs.iter().map(|cd| cd.0.as_str()))
+            .filter(|op| run.starts_with(op))
+            .map(str::len)
+            .max()
+            .unwrap_or_else(|| run.chars().next().map_or(0, char::len_utf8))
+    }
+
+    /// Reads a string or character literal, the cursor being just past
+    /// the opening `quote`. Literals end at the closing quote, one that
+    /// reaches the end of the line is unterminated.
+    fn literal(&mut self, quote: char, tok_begin: usize) -> Token<'a> {
+        let begin = self.cursor;
+        let mut chars = self.text[begin..].char_indices().peekable();
+        let mut value = String::new();
+        let mut bad_escapes = vec![];
+        let mut end = None;
+        let mut stop = self.text.len();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                _ if c == quote => {
+                    end = Some(begin + i);
+                    break;
+                },
+                '\r' | '\n' => {
+                    stop = begin + i;
+                    break;
+                },
+                '\\' => match Self::escape(&mut chars) {
+                    Some(decoded) => value.push(decoded),
+                    None => {
+                        let esc_end = chars.peek().map_or(self.text.len(), |(j, _)| begin + j);
+                        bad_escapes.push((begin + i, esc_end));
+                    }
+                },
+                _ => value.push(c)
+            }
+        }
+
+        let (sval, ttype) = match end {
+            Some(end) => {
+                self.cursor = end + quote.len_utf8();
+                let ttype = if quote == '\'' { TokenType::Char } else { TokenType::String };
+                (&self.text[begin..end], ttype)
+            },
+            None => {
+                self.cursor = stop;
+                (&self.text[begin..stop], TokenType::Undefined)
+            }
+        };
+
+        let span = self.span(tok_begin, self.cursor);
+        if end.is_none() {
+            self.diagnostics.push(Diagnostic::error(Code::UnterminatedLiteral, span, &[sval]));
+        }
+        for &(esc_begin, esc_end) in &bad_escapes {
+            let esc_span = self.span(esc_begin, esc_end);
+            self.diagnostics.push(Diagnostic::error(Code::InvalidEscape, esc_span, &[&self.text[esc_begin..esc_end]]));
+        }
+
+        if ttype == TokenType::Undefined || !bad_escapes.is_empty() {
+            return Token { sval, nval: 0.0, ttype: TokenType::Undefined, span, value: None, cval: None };
+        }
+
+        let mut cval = None;
+        if ttype == TokenType::Char {
+            // a letter with its accent typed separately is still one
+            let composed = nfc(&value);
+            let mut chars = composed.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => cval = Some(c),
+                _ => {
+                    self.diagnostics.push(Self::invalid_char(&value, span));
+                    return Token { sval, nval: 0.0, ttype: TokenType::Undefined, span, value: None, cval: None };
+                }
+            }
+        }
+        Token { sval, nval: 0.0, ttype, span, value: Some(value), cval }
+    }
+
+    /// A character literal holds a single `char`. Letters written with a
+    /// separate combining accent that has no precomposed form look like
+    /// one character but aren't, so they get their own explanation.
+    fn invalid_char(value: &str, span: Span) -> Diagnostic {
+        let count = value.chars().count();
+        let d = Diagnostic::error(Code::InvalidChar, span, &[value])
+            .with_label(Message::new(Phrase::CharCount, count.to_string()));
+
+        if count > 1 && value.chars().skip(1).all(is_combining_mark) {
+            d.with_note(Phrase::CombiningMark)
+        } else if count > 1 {
+            d.with_note(Phrase::UseString)
+        } else {
+            d
+        }
+    }
+
+    /// Decodes the escape sequence following a backslash: `\n`, `\t`,
+    /// `\\`, `\"`, `\'`, `\u{...}` with at most six hex digits, or `\u`
+    /// followed directly by at most four. `None` if it's malformed, the
+    /// characters read up to that point are consumed.
+    fn escape(chars: &mut Peekable<CharIndices>) -> Option<char> {
+        // a line break can't be escaped, it ends the literal
+        let (_, c) = chars.next_if(|&(_, c)| c != '\r' && c != '\n')?;
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '\\' | '"' | '\'' => Some(c),
+            'u' => {
+                let braced = chars.next_if(|&(_, c)| c == '{').is_some();
+                let max_digits = if braced { 6 } else { 4 };
+
+                let mut code: u32 = 0;
+                let mut digits = 0;
+                while digits < max_digits {
+                    match chars.peek().and_then(|&(_, c)| c.to_digit(16)) {
+                        Some(d) => code = code * 16 + d,
+                        None => break
+                    }
+                    chars.next();
+                    digits += 1;
+                }
+
+                if digits == 0 || (braced && chars.next_if(|&(_, c)| c == '}').is_none()) {
+                    return None;
+                }
+                // surrogates and values past U+10FFFF are not characters
+                char::from_u32(code)
+            },
+            _ => None
         }
     }
 }
@@ -88,24 +510,18 @@ impl<'a> Iterator for Tokenizer<'a> {
         let mut chars = self.text[self.cursor..].chars();
         let mut char_it = chars.next();
         
-        while char_it.is_some() && char_it.unwrap().is_whitespace() {
-            let c = char_it.unwrap();
+        while let Some(c) = char_it.filter(|c| c.is_whitespace()) {
+            let eol_begin = self.cursor;
             self.cursor += c.len_utf8();
             
             if self.eol_matters { 
                 let eol = match c {
                     '\r' => {
-                        let pk = chars.by_ref()
-                            .peekable().peek()
-                            .unwrap_or(&'\0')
-                            .clone();
-
-                        if pk != '\n' { 
-                            "\r" 
-                        } else {
-                            chars.next();
+                        if self.text[self.cursor..].starts_with('\n') {
                             self.cursor += 1;
                             "\r\n"
+                        } else {
+                            "\r"
                         }
                     },
                     '\n' => "\n",
@@ -113,66 +529,37 @@ impl<'a> Iterator for Tokenizer<'a> {
                 };
 
                 if !eol.is_empty() {
-                    let tk = Token::from_str(eol, TokenType::LineBreak);
-                    return Option::from(tk);
+                    let span = self.span(eol_begin, self.cursor);
+                    return Some(Token { sval: eol, nval: 0.0, ttype: TokenType::LineBreak, span, value: None, cval: None });
                 }
             }
 
             char_it = chars.next();
         }
         
-        if char_it.is_none() {
-            return Option::from(None);
-        }
-
         let mut ttype: TokenType;
+        let mut err: Option<Code> = None;
         let begin: usize;
         let end: usize;
-        let start_char = char_it.unwrap();
+        let start_char = char_it?;
+        let tok_begin = self.cursor;
 
         self.cursor += start_char.len_utf8();
 
         if start_char == '"' || start_char == '\'' {
-            begin = self.cursor + start_char.len_utf8();
-            let str_marker = start_char;
-
-            for c in chars.by_ref() {
-                if c == str_marker { break; }
-                self.cursor += c.len_utf8();
-            }
-
-            if chars.next().is_none() {
-                ttype = TokenType::Undefined;
-                end = self.cursor;
-            }
-            else {
-                ttype = if str_marker == '\'' { TokenType::Char } else { TokenType::String };
-                end = self.cursor;
-                self.cursor += str_marker.len_utf8();
-            }
+            return Some(self.literal(start_char, tok_begin));
         }
-        else if start_char.is_numeric() {
-            begin = self.cursor;
-            let mut decimal_set = false;
-                
-            for c in chars {
-                if !decimal_set && (c == ',' || c == '.') {
-                    decimal_set = true;
-                    self.cursor += c.len_utf8();
-                    continue;
-                }
-                if !c.is_numeric() { break; }
-                self.cursor += c.len_utf8();
-            }
 
-            ttype = if decimal_set { TokenType::Float } else { TokenType::Int };
-            end = self.cursor;
+        if start_char.is_numeric() {
+            return Some(self.number(tok_begin));
         }
-        else if start_char.is_alphanumeric()  || start_char == '_' {
+
+        if start_char.is_alphanumeric()  || start_char == '_' {
             begin = self.cursor;
 
+            // accents typed as combining marks are part of the word
             for c in chars {
-                if c != '_' && !c.is_alphanumeric() { break; }
+                if c != '_' && !c.is_alphanumeric() && !is_combining_mark(c) { break; }
                 self.cursor += c.len_utf8();
             }
 
@@ -192,22 +579,24 @@ impl<'a> Iterator for Tokenizer<'a> {
                 }
                 self.cursor += c.len_utf8();
             }
+            if self.concat_special && !self.operators.is_empty() {
+                self.cursor = tok_begin + self.munch(&self.text[tok_begin..self.cursor]);
+            }
             end = self.cursor;
         }
 
         let sval: &str = &self.text[begin - start_char.len_utf8()..end];
 
         // parse comment
-        let def = self.comment_defs.iter().find(|cd| cd.0 == sval);
-        if def.is_some() {
-            let cmt_end = def.unwrap().1;
+        if let Some(def) = self.comment_defs.iter().find(|cd| cd.0 == sval) {
+            let cmt_end = def.1.clone();
             let begin = self.cursor;
 
             // no ending specified means until line break
             let opt_end = if cmt_end.is_empty() {
                 self.text[begin..].find(&['\r', '\n'][..])
             } else {
-                self.text[begin..].find(cmt_end)
+                self.text[begin..].find(&cmt_end)
             };
 
             let end = match opt_end {
@@ -216,40 +605,50 @@ impl<'a> Iterator for Tokenizer<'a> {
             };
 
             let sval = &self.text[begin..end];
-            self.cursor = end + cmt_end.len();
-            return Option::from(Token::from(sval, 0.0, TokenType::Comment));
+            self.cursor = (end + cmt_end.len()).min(self.text.len());
+            let span = self.span(tok_begin, self.cursor);
+            if opt_end.is_none() && !cmt_end.is_empty() {
+                let open = self.span(tok_begin, begin);
+                self.diagnostics.push(Diagnostic::error(Code::UnterminatedComment, open, &[&cmt_end]));
+            }
+            return Some(Token { sval, nval: 0.0, ttype: TokenType::Comment, span, value: None, cval: None });
         }
 
-        let mut nval: f64 = 0.0;
-
-        if ttype == TokenType::Char && !sval.len() == 1 {
-            ttype = TokenType::Undefined;
-        } 
-        else if ttype == TokenType::Float {
-            nval = sval
-                .replace(',', ".")
-                .parse::<f64>()
-                .unwrap_or(f64::NAN);
-            if nval.is_nan() { ttype = TokenType::Undefined }
-        } 
-        else if ttype == TokenType::Word && 
+        if ttype == TokenType::Word && 
                 !sval.chars().any(char::is_alphanumeric) 
         {
             // only underscores don't count as words
             ttype = TokenType::Undefined;
+            err = Some(Code::InvalidWord);
         } 
-        else if ttype == TokenType::Int {
-            nval = sval.parse::<i64>().unwrap() as f64;
+        else if ttype == TokenType::Special {
+            for (i, c) in sval.char_indices() {
+                match c {
+                    '(' => {
+                        let call = self.is_call(tok_begin + i);
+                        self.brackets.push(call);
+                    },
+                    '[' => self.brackets.push(true),
+                    ')' | ']' => { self.brackets.pop(); },
+                    _ => ()
+                }
+            }
         }
 
-        return Option::from(Token::from(sval, nval, ttype));
+        let span = self.span(tok_begin, self.cursor);
+        if let Some(code) = err {
+            self.diagnostics.push(Diagnostic::error(code, span, &[sval]));
+        }
+        Some(Token { sval, nval: 0.0, ttype, span, value: None, cval: None })
     }
 }
 
 // Unit tests
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
+    use super::super::dialect::Dialect;
 
     macro_rules! assert_vec_eq {
         ($v1:ident, $v2:ident) => {
@@ -353,4 +752,236 @@ mod tests {
         dbg!(&result);
         assert_vec_eq!(result, expect);
     }
+
+    #[test]
+    fn block_comments() {
+        let s = "a (* one\ntwo *) b (* open";
+        let options = Tokenizer::CONCAT_SPECIAL | Tokenizer::EOL_MATTERS;
+        let mut tok = Tokenizer::with_comments(s, options, vec![(String::from("(*"), String::from("*)"))]);
+        let result: Vec<(&str, Span)> = tok.by_ref().map(|t| (t.sval, t.span)).collect();
+
+        let at = |begin, end, line, col| Span { begin, end, line, col };
+        assert_eq!(result, [
+            ("a", at(0, 1, 1, 1)),
+            (" one\ntwo ", at(2, 15, 1, 3)),
+            ("b", at(16, 17, 2, 8)),
+            (" open", at(18, 25, 2, 10)),
+        ]);
+        let diags = tok.take_diagnostics();
+        assert_eq!(diags.len(), 1);
+        assert_eq!((diags[0].code, diags[0].span()), (Code::UnterminatedComment, at(18, 20, 2, 10)));
+    }
+
+    #[test]
+    fn escapes() {
+        let s = r#""a \"b\" \\ \n\t" 'ő' '\'' " vmi \u644 \u{1F600}" "#;
+        let values: Vec<Option<String>> = Tokenizer::from(s).map(|t| t.value).collect();
+
+        let expect = [
+            "a \"b\" \\ \n\t", "ő", "'", " vmi \u{644} \u{1F600}"
+        ];
+        assert_eq!(values, expect.iter().map(|v| Some(v.to_string())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn invalid_literals() {
+        let s = "\"a\\q\" \"\\u{d800}\" \"\\u{12\" \"x\n\"y";
+        let mut tok = Tokenizer::with_options(s, Tokenizer::EOL_MATTERS);
+        let result: Vec<Token> = tok.by_ref().collect();
+
+        let expect = [
+            Token::from_str("a\\q", TokenType::Undefined),
+            Token::from_str("\\u{d800}", TokenType::Undefined),
+            Token::from_str("\\u{12", TokenType::Undefined),
+            Token::from_str("x", TokenType::Undefined),
+            Token::from_str("\n", TokenType::LineBreak),
+            Token::from_str("y", TokenType::Undefined),
+        ];
+        assert_vec_eq!(result, expect);
+
+        let diags: Vec<(Code, &str)> = tok.take_diagnostics().iter()
+            .map(|d| (d.code, &s[d.primary.span.begin..d.primary.span.end]))
+            .collect();
+        assert_eq!(diags, [
+            (Code::InvalidEscape, "\\q"),
+            (Code::InvalidEscape, "\\u{d800}"),
+            (Code::InvalidEscape, "\\u{12"),
+            (Code::UnterminatedLiteral, "\"x"),
+            (Code::UnterminatedLiteral, "\"y"),
+        ]);
+    }
+
+    #[test]
+    fn char_literals() {
+        let s = "'ő' '\\n' '\\u{1F600}' 'ab' '' 'o\u{30B}' 'x\u{30B}'";
+        let mut tok = Tokenizer::from(s);
+        let result: Vec<(TokenType, Option<char>)> = tok.by_ref().map(|t| (t.ttype, t.cval)).collect();
+
+        assert_eq!(result, [
+            (TokenType::Char, Some('ő')),
+            (TokenType::Char, Some('\n')),
+            (TokenType::Char, Some('\u{1F600}')),
+            (TokenType::Undefined, None),
+            (TokenType::Undefined, None),
+            (TokenType::Char, Some('ő')),
+            (TokenType::Undefined, None),
+        ]);
+
+        let diags = tok.take_diagnostics();
+        assert!(diags.iter().all(|d| d.code == Code::InvalidChar));
+        let notes: Vec<Vec<Phrase>> = diags.iter()
+            .map(|d| d.context.notes.iter().map(|n| n.phrase).collect())
+            .collect();
+        assert_eq!(notes, [vec![Phrase::UseString], vec![], vec![Phrase::CombiningMark]]);
+    }
+
+    fn numbers(s: &str, numbers: NumberPolicy) -> Vec<Token<'_>> {
+        let mut tok = Tokenizer::with_options(s, Tokenizer::CONCAT_SPECIAL);
+        tok.set_number_policy(numbers);
+        tok.collect()
+    }
+
+    #[test]
+    fn number_policies() {
+        let s = "3,25 f(1,5) 2.5 (2,75 * t[1,5])";
+
+        let expect = [
+            Token::from("3,25", 3.25, TokenType::Float),
+            Token::from_str("f", TokenType::Word),
+            Token::from_str("(", TokenType::Special),
+            Token::from("1", 1.0, TokenType::Int),
+            Token::from_str(",", TokenType::Special),
+            Token::from("5", 5.0, TokenType::Int),
+            Token::from_str(")", TokenType::Special),
+            Token::from("2.5", 2.5, TokenType::Float),
+            Token::from_str("(", TokenType::Special),
+            Token::from("2,75", 2.75, TokenType::Float),
+            Token::from_str("*", TokenType::Special),
+            Token::from_str("t", TokenType::Word),
+            Token::from_str("[", TokenType::Special),
+            Token::from("1", 1.0, TokenType::Int),
+            Token::from_str(",", TokenType::Special),
+            Token::from("5", 5.0, TokenType::Int),
+            Token::from_str("])", TokenType::Special),
+        ];
+        let result = numbers(s, NumberPolicy::Hungarian);
+        assert_vec_eq!(result, expect);
+
+        // keywords are not function names
+        let mut tok = Tokenizer::with_options("HA (x > 1,5) AKKOR CIKLUS AMÍG (i < 2,5)", Tokenizer::CONCAT_SPECIAL);
+        tok.set_keywords(Dialect::hungarian().keywords());
+        let floats: Vec<&str> = tok.filter(|t| t.ttype == TokenType::Float).map(|t| t.sval).collect();
+        assert_eq!(floats, ["1,5", "2,5"]);
+
+        let result = numbers(s, NumberPolicy::Comma);
+        assert_eq!(result[0], Token::from("3,25", 3.25, TokenType::Float));
+        assert_eq!(result[3], Token::from("1,5", 1.5, TokenType::Float));
+        assert_eq!(result[5], Token::from_str("2.5", TokenType::Undefined));
+
+        let result = numbers(s, NumberPolicy::Dot);
+        assert_eq!(result[0], Token::from("3", 3.0, TokenType::Int));
+        assert_eq!(result[1], Token::from_str(",", TokenType::Special));
+        assert_eq!(result[9], Token::from("2.5", 2.5, TokenType::Float));
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let s = "1.5e3 2E-2 7e 123.0.0,5 12ab 1e400";
+        let mut tok = Tokenizer::from(s);
+        let result: Vec<Token> = tok.by_ref().collect();
+
+        let expect = [
+            Token::from("1.5e3", 1500.0, TokenType::Float),
+            Token::from("2E-2", 0.02, TokenType::Float),
+            Token::from_str("7e", TokenType::Undefined),
+            Token::from_str("123.0.0,5", TokenType::Undefined),
+            Token::from_str("12ab", TokenType::Undefined),
+            Token::from_str("1e400", TokenType::Undefined),
+        ];
+        assert_vec_eq!(result, expect);
+
+        let labels: Vec<Phrase> = tok.take_diagnostics().iter()
+            .map(|d| d.primary.message.as_ref().unwrap().phrase)
+            .collect();
+        assert_eq!(labels, [
+            Phrase::ExponentDigits, Phrase::ExtraSeparator, Phrase::NumberSuffix, Phrase::NumberTooLarge
+        ]);
+    }
+
+    #[test]
+    fn other_digits() {
+        let s = "x := ٣٤ ½";
+        let mut tok = Tokenizer::with_options(s, Tokenizer::CONCAT_SPECIAL);
+        let types: Vec<TokenType> = tok.by_ref().map(|t| t.ttype).collect();
+        assert_eq!(types, [TokenType::Word, TokenType::Special, TokenType::Undefined, TokenType::Undefined]);
+
+        let diags: Vec<(Code, &str)> = tok.take_diagnostics().iter()
+            .map(|d| (d.code, &s[d.primary.span.begin..d.primary.span.end]))
+            .collect();
+        assert_eq!(diags, [(Code::InvalidNumber, "٣٤"), (Code::InvalidNumber, "½")]);
+    }
+
+    /// Anything a student may hand in has to come out as tokens, never
+    /// as a panic.
+    #[test]
+    fn arbitrary_input() {
+        use super::super::lexer::Lexer;
+
+        let inputs = [
+            "", "\r", "\r\n\r", "\"", "'", "\\", "\"\\", "'\\u{", "\"\\u{110000}\"",
+            "x := 1234567890123456789012345", "٣٤ ½ x²", "1e", "1.e5", "0,,5",
+            "((((", "))))", "** comment at eof", "汉\u{300}", "9223372036854775808",
+        ];
+        for s in inputs {
+            let mut lx = Lexer::from(s);
+            let count = lx.by_ref().count();
+            assert!(count <= s.len() + 1, "{:?}", s);
+            lx.take_diagnostics();
+        }
+
+        let result: Vec<Token> = Tokenizer::from("1234567890123456789012345 9223372036854775807").collect();
+        assert_eq!(result[0].ttype, TokenType::Undefined);
+        assert_eq!(result[1], Token::from("9223372036854775807", i64::MAX as f64, TokenType::Int));
+    }
+
+    #[test]
+    fn maximal_munch() {
+        let s = "a:=(b)*(c)<=-1 x:==y $+ 2**c";
+        let options = Tokenizer::CONCAT_SPECIAL;
+        let mut tok = Tokenizer::with_comments(s, options, vec![("**", "")]);
+        tok.set_operators(vec![":=", "(", ")", "*", "<", "<=", "-", "=", "+"]);
+        let result: Vec<&str> = tok.map(|t| t.sval).collect();
+
+        assert_eq!(result, [
+            "a", ":=", "(", "b", ")", "*", "(", "c", ")", "<=", "-", "1",
+            "x", ":=", "=", "y", "$", "+", "2", "c"
+        ]);
+    }
+
+    #[test]
+    fn spans() {
+        let s = "ab :=\r\n  \"é\" 12\r\rx ** c\n汉y";
+        let comment_defs = vec![("**", "")];
+        let options = Tokenizer::CONCAT_SPECIAL | Tokenizer::EOL_MATTERS;
+        let result: Vec<Span> = Tokenizer::with_comments(s, options, comment_defs)
+            .map(|t| t.span)
+            .collect();
+
+        let at = |begin, end, line, col| Span { begin, end, line, col };
+        let expect = [
+            at(0, 2, 1, 1),     // ab
+            at(3, 5, 1, 4),     // :=
+            at(5, 7, 1, 6),     // \r\n
+            at(9, 13, 2, 3),    // "é"
+            at(14, 16, 2, 7),   // 12
+            at(16, 17, 2, 9),   // \r
+            at(17, 18, 3, 1),   // \r
+            at(18, 19, 4, 1),   // x
+            at(20, 24, 4, 3),   // ** c
+            at(24, 25, 4, 7),   // \n
+            at(25, 29, 5, 1),   // 汉y
+        ];
+
+        assert_vec_eq!(result, expect);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,877 @@
+use std::any::Any;
+use super::variable::{ Type, TypeName };
+use super::unary_operator::UnaryOperator;
+use super::binary_operator::BinaryOperator;
+use super::environment::RuntimeError;
+use super::ast::Literal;
+
+pub struct IntType(pub i64);
+pub struct FloatType(pub f64);
+pub struct StringType(pub String);
+pub struct CharType(pub char);
+pub struct BoolType(pub bool);
+
+/// Elements of a `TÖMB`. PLang indexes them from 1.
+pub struct ArrayType {
+    pub elem: TypeName,
+    pub items: Vec<Box<dyn Type>>
+}
+
+type TypeResult = Result<Box<dyn Type>, RuntimeError>;
+
+impl Literal {
+    pub fn value(&self) -> Box<dyn Type> {
+        match self {
+            Literal::Int(i) => Box::new(IntType(*i)),
+            Literal::Float(f) => Box::new(FloatType(*f)),
+            Literal::String(s) => Box::new(StringType(s.clone())),
+            Literal::Char(c) => Box::new(CharType(*c)),
+            Literal::Bool(b) => Box::new(BoolType(*b))
+        }
+    }
+}
+
+/// Text as it's written between `quote`s in the source, the inverse of
+/// the tokenizer's escape decoding.
+fn escape_literal(s: &str, quote: char) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\\' => out.push_str("\\\\"),
+            _ if c == quote => {
+                out.push('\\');
+                out.push(c);
+            },
+            _ => out.push(c)
+        }
+    }
+    out
+}
+
+fn downcast<T: 'static>(var: &dyn Type) -> Option<&T> {
+    var.as_any().downcast_ref::<T>()
+}
+
+fn unsupported(op: &'static str, var: &dyn Type) -> RuntimeError {
+    RuntimeError::Operator(op, var.type_name().name())
+}
+
+/// Comparison operators work the same way on every ordered type.
+fn compare<T: PartialOrd>(op: BinaryOperator, a: &T, b: &T) -> Option<bool> {
+    match op {
+        BinaryOperator::Eq => Some(a == b),
+        BinaryOperator::Ne => Some(a != b),
+        BinaryOperator::Lt => Some(a < b),
+        BinaryOperator::Gt => Some(a > b),
+        BinaryOperator::Le => Some(a <= b),
+        BinaryOperator::Ge => Some(a >= b),
+        _ => None
+    }
+}
+
+const fn is_comparison(op: BinaryOperator) -> bool {
+    matches!(op,
+        BinaryOperator::Eq | BinaryOperator::Ne |
+        BinaryOperator::Lt | BinaryOperator::Gt |
+        BinaryOperator::Le | BinaryOperator::Ge)
+}
+
+/// Numeric value of an EGÉSZ or VALÓS, which can be mixed in arithmetic.
+fn as_float(var: &dyn Type) -> Option<f64> {
+    match var.type_name() {
+        TypeName::Int | TypeName::Float => Some(var.const_nval()),
+        _ => None
+    }
+}
+
+fn float_result(op: &'static str, r: f64) -> TypeResult {
+    if r.is_nan() {
+        Err(RuntimeError::Domain(op))
+    } else if r.is_infinite() {
+        Err(RuntimeError::Overflow)
+    } else {
+        Ok(Box::new(FloatType(r)))
+    }
+}
+
+/// Float to EGÉSZ conversion, failing if the value doesn't fit.
+fn to_int(f: f64) -> TypeResult {
+    if f.is_finite() && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Ok(Box::new(IntType(f as i64)))
+    } else {
+        Err(RuntimeError::Overflow)
+    }
+}
+
+/// Result type of the numeric functions (`SIN`, `EGÉSZ`, `| |`, ...)
+/// shared by EGÉSZ and VALÓS operands.
+fn numeric_optype(op: UnaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+    match op {
+        UnaryOperator::Minus | UnaryOperator::Pipe => Some(var.init_val()),
+        UnaryOperator::Trunc | UnaryOperator::Round => Some(TypeName::Int.init_val()),
+        UnaryOperator::Sin | UnaryOperator::Cos | UnaryOperator::Tan |
+        UnaryOperator::ArcSin | UnaryOperator::ArcCos | UnaryOperator::ArcTan |
+        UnaryOperator::Log | UnaryOperator::Exp |
+        UnaryOperator::Real => Some(TypeName::Float.init_val()),
+        _ => None
+    }
+}
+
+fn numeric_apply(op: UnaryOperator, f: f64) -> TypeResult {
+    let r = match op {
+        UnaryOperator::Sin => f.sin(),
+        UnaryOperator::Cos => f.cos(),
+        UnaryOperator::Tan => f.tan(),
+        UnaryOperator::ArcSin => f.asin(),
+        UnaryOperator::ArcCos => f.acos(),
+        UnaryOperator::ArcTan => f.atan(),
+        UnaryOperator::Log if f <= 0.0 => return Err(RuntimeError::Domain(op.op())),
+        UnaryOperator::Log => f.ln(),
+        UnaryOperator::Exp => f.exp(),
+        UnaryOperator::Real => f,
+        UnaryOperator::Trunc => return to_int(f.trunc()),
+        UnaryOperator::Round => return to_int(f.round()),
+        _ => return Err(RuntimeError::Operator(op.op(), TypeName::Float.name()))
+    };
+    float_result(op.op(), r)
+}
+
+/// Case conversion can yield several characters (e.g. 'ß' to "SS"), 
+/// a KARAKTER is kept as it is then.
+fn single(mut it: impl Iterator<Item = char>, c: char) -> char {
+    match (it.next(), it.next()) {
+        (Some(r), None) => r,
+        _ => c
+    }
+}
+
+/// `a @ b` glues together the printed form of any two values.
+fn concat(a: &dyn Type, b: &dyn Type) -> Box<dyn Type> {
+    let mut s = String::new();
+    a.print_data(&mut s);
+    b.print_data(&mut s);
+    Box::new(StringType(s))
+}
+
+impl Type for IntType {
+    fn type_name(&self) -> TypeName { TypeName::Int }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.0.to_string() }
+    fn const_nval(&self) -> f64 { self.0 as f64 }
+    fn render(&self) -> String { self.0.to_string() }
+
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        var.type_name() == TypeName::Int
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        match downcast::<IntType>(var) {
+            Some(i) => Box::new(IntType(i.0)),
+            None => self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> { Box::new(IntType(0)) }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        numeric_optype(op, self)
+    }
+
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        let r = match op {
+            UnaryOperator::Minus => self.0.checked_neg(),
+            UnaryOperator::Pipe => self.0.checked_abs(),
+            UnaryOperator::Trunc | UnaryOperator::Round => Some(self.0),
+            _ => return numeric_apply(op, self.0 as f64)
+        };
+        r.map(|i| Box::new(IntType(i)) as Box<dyn Type>)
+            .ok_or(RuntimeError::Overflow)
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        if op == BinaryOperator::At {
+            return Some(TypeName::String.init_val());
+        }
+        if var.type_name() == TypeName::Float {
+            return FloatType(self.0 as f64).binary_optype(op, var);
+        }
+        if var.type_name() != TypeName::Int {
+            return None;
+        }
+        match op {
+            BinaryOperator::Plus | BinaryOperator::Minus |
+            BinaryOperator::Star | BinaryOperator::Hat |
+            BinaryOperator::Div | BinaryOperator::Mod => Some(self.init_val()),
+            BinaryOperator::Slash => Some(TypeName::Float.init_val()),
+            _ if is_comparison(op) => Some(TypeName::Bool.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        if op == BinaryOperator::At {
+            return Ok(concat(self, var));
+        }
+        if var.type_name() == TypeName::Float {
+            return FloatType(self.0 as f64).binary_apply(op, var);
+        }
+        let (a, b) = match downcast::<IntType>(var) {
+            Some(b) => (self.0, b.0),
+            None => return Err(unsupported(op.op(), var))
+        };
+        if let Some(r) = compare(op, &a, &b) {
+            return Ok(Box::new(BoolType(r)));
+        }
+
+        let r = match op {
+            BinaryOperator::Plus => a.checked_add(b),
+            BinaryOperator::Minus => a.checked_sub(b),
+            BinaryOperator::Star => a.checked_mul(b),
+            BinaryOperator::Div | BinaryOperator::Mod if b == 0 => {
+                return Err(RuntimeError::DivByZero)
+            },
+            BinaryOperator::Div => a.checked_div(b),
+            BinaryOperator::Mod => a.checked_rem(b),
+            BinaryOperator::Slash if b == 0 => return Err(RuntimeError::DivByZero),
+            BinaryOperator::Slash => return Ok(Box::new(FloatType(a as f64 / b as f64))),
+            // `EGÉSZ ^ EGÉSZ` is an EGÉSZ, which a negative power isn't
+            BinaryOperator::Hat if b < 0 && a == 0 => return Err(RuntimeError::DivByZero),
+            BinaryOperator::Hat if b < 0 => return Err(RuntimeError::Domain(op.op())),
+            BinaryOperator::Hat => {
+                if b > u32::MAX as i64 { None } else { a.checked_pow(b as u32) }
+            },
+            _ => return Err(unsupported(op.op(), self))
+        };
+
+        r.map(|i| Box::new(IntType(i)) as Box<dyn Type>)
+            .ok_or(RuntimeError::Overflow)
+    }
+
+    fn parse_data(&self, s: &str) -> Option<Box<dyn Type>> {
+        s.trim().parse::<i64>().ok().map(|i| Box::new(IntType(i)) as Box<dyn Type>)
+    }
+}
+
+impl Type for FloatType {
+    fn type_name(&self) -> TypeName { TypeName::Float }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.0.to_string() }
+    fn const_nval(&self) -> f64 { self.0 }
+    fn render(&self) -> String { format!("{:?}", self.0) }
+
+    /// EGÉSZ values widen to VALÓS.
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        as_float(var).is_some()
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        match as_float(var) {
+            Some(f) => Box::new(FloatType(f)),
+            None => self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> { Box::new(FloatType(0.0)) }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        numeric_optype(op, self)
+    }
+
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        match op {
+            UnaryOperator::Minus => Ok(Box::new(FloatType(-self.0))),
+            UnaryOperator::Pipe => Ok(Box::new(FloatType(self.0.abs()))),
+            _ => numeric_apply(op, self.0)
+        }
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        if op == BinaryOperator::At {
+            return Some(TypeName::String.init_val());
+        }
+        as_float(var)?;
+        match op {
+            BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::Star |
+            BinaryOperator::Slash | BinaryOperator::Hat => Some(self.init_val()),
+            _ if is_comparison(op) => Some(TypeName::Bool.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        if op == BinaryOperator::At {
+            return Ok(concat(self, var));
+        }
+        let (a, b) = match as_float(var) {
+            Some(b) => (self.0, b),
+            None => return Err(unsupported(op.op(), var))
+        };
+        if let Some(r) = compare(op, &a, &b) {
+            return Ok(Box::new(BoolType(r)));
+        }
+
+        let r = match op {
+            BinaryOperator::Plus => a + b,
+            BinaryOperator::Minus => a - b,
+            BinaryOperator::Star => a * b,
+            BinaryOperator::Slash if b == 0.0 => return Err(RuntimeError::DivByZero),
+            BinaryOperator::Slash => a / b,
+            BinaryOperator::Hat if a == 0.0 && b < 0.0 => return Err(RuntimeError::DivByZero),
+            BinaryOperator::Hat => a.powf(b),
+            _ => return Err(unsupported(op.op(), self))
+        };
+        float_result(op.op(), r)
+    }
+
+    /// Accepts both `3.14` and `3,14`.
+    fn parse_data(&self, s: &str) -> Option<Box<dyn Type>> {
+        s.trim().replace(',', ".").parse::<f64>().ok()
+            .filter(|f| f.is_finite())
+            .map(|f| Box::new(FloatType(f)) as Box<dyn Type>)
+    }
+}
+
+impl Type for StringType {
+    fn type_name(&self) -> TypeName { TypeName::String }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.0.clone() }
+    fn const_nval(&self) -> f64 { 0.0 }
+    fn render(&self) -> String { format!("\"{}\"", escape_literal(&self.0, '"')) }
+
+    /// KARAKTER values widen to SZÖVEG.
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        var.type_name() == TypeName::String || var.type_name() == TypeName::Char
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        if self.can_copy(var) {
+            Box::new(StringType(var.const_sval()))
+        } else {
+            self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> { Box::new(StringType(String::new())) }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        match op {
+            UnaryOperator::Upper | UnaryOperator::Lower => Some(self.init_val()),
+            UnaryOperator::Pipe | UnaryOperator::Trunc => Some(TypeName::Int.init_val()),
+            UnaryOperator::Real => Some(TypeName::Float.init_val()),
+            _ => None
+        }
+    }
+
+    /// Besides `NAGY`/`KIS`, the length `|s|` and parsing numbers 
+    /// with `EGÉSZ(s)`/`VALÓS(s)` are available.
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        match op {
+            UnaryOperator::Upper => Ok(Box::new(StringType(self.0.to_uppercase()))),
+            UnaryOperator::Lower => Ok(Box::new(StringType(self.0.to_lowercase()))),
+            UnaryOperator::Pipe => Ok(Box::new(IntType(self.0.chars().count() as i64))),
+            UnaryOperator::Trunc => IntType(0).parse_data(&self.0)
+                .ok_or_else(|| RuntimeError::Input(self.0.clone(), TypeName::Int.name())),
+            UnaryOperator::Real => FloatType(0.0).parse_data(&self.0)
+                .ok_or_else(|| RuntimeError::Input(self.0.clone(), TypeName::Float.name())),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        if op == BinaryOperator::At {
+            return Some(self.init_val());
+        }
+        match (op, var.type_name()) {
+            (BinaryOperator::Plus, TypeName::String) |
+            (BinaryOperator::Plus, TypeName::Char) => Some(self.init_val()),
+            (_, TypeName::String) if is_comparison(op) => Some(TypeName::Bool.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        if op == BinaryOperator::At {
+            return Ok(concat(self, var));
+        }
+        if op == BinaryOperator::Plus && var.type_name() == TypeName::Char {
+            return Ok(concat(self, var));
+        }
+        let b = match downcast::<StringType>(var) {
+            Some(b) => &b.0,
+            None => return Err(unsupported(op.op(), var))
+        };
+        if let Some(r) = compare(op, &self.0, b) {
+            return Ok(Box::new(BoolType(r)));
+        }
+
+        match op {
+            BinaryOperator::Plus => Ok(Box::new(StringType(self.0.clone() + b))),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn print_data(&self, s: &mut String) { s.push_str(&self.0) }
+
+    fn parse_data(&self, s: &str) -> Option<Box<dyn Type>> {
+        Some(Box::new(StringType(s.to_string())))
+    }
+}
+
+impl Type for CharType {
+    fn type_name(&self) -> TypeName { TypeName::Char }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.0.to_string() }
+    fn const_nval(&self) -> f64 { self.0 as u32 as f64 }
+    fn render(&self) -> String { format!("'{}'", escape_literal(&self.0.to_string(), '\'')) }
+
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        var.type_name() == TypeName::Char
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        match downcast::<CharType>(var) {
+            Some(c) => Box::new(CharType(c.0)),
+            None => self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> { Box::new(CharType(' ')) }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        match op {
+            UnaryOperator::Upper | UnaryOperator::Lower => Some(self.init_val()),
+            UnaryOperator::IsNum | UnaryOperator::IsAlpha => Some(TypeName::Bool.init_val()),
+            _ => None
+        }
+    }
+
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        match op {
+            UnaryOperator::Upper => Ok(Box::new(CharType(single(self.0.to_uppercase(), self.0)))),
+            UnaryOperator::Lower => Ok(Box::new(CharType(single(self.0.to_lowercase(), self.0)))),
+            UnaryOperator::IsNum => Ok(Box::new(BoolType(self.0.is_ascii_digit()))),
+            UnaryOperator::IsAlpha => Ok(Box::new(BoolType(self.0.is_alphabetic()))),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        if op == BinaryOperator::At {
+            return Some(TypeName::String.init_val());
+        }
+        match (op, var.type_name()) {
+            (BinaryOperator::Plus, TypeName::Char) |
+            (BinaryOperator::Plus, TypeName::String) => Some(TypeName::String.init_val()),
+            (_, TypeName::Char) if is_comparison(op) => Some(TypeName::Bool.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        if op == BinaryOperator::At {
+            return Ok(concat(self, var));
+        }
+        if op == BinaryOperator::Plus && 
+           (var.type_name() == TypeName::Char || var.type_name() == TypeName::String)
+        {
+            return Ok(concat(self, var));
+        }
+        let b = match downcast::<CharType>(var) {
+            Some(b) => b.0,
+            None => return Err(unsupported(op.op(), var))
+        };
+        match compare(op, &self.0, &b) {
+            Some(r) => Ok(Box::new(BoolType(r))),
+            None => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn print_data(&self, s: &mut String) { s.push(self.0) }
+
+    fn parse_data(&self, s: &str) -> Option<Box<dyn Type>> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(Box::new(CharType(c))),
+            _ => None
+        }
+    }
+}
+
+impl Type for BoolType {
+    fn type_name(&self) -> TypeName { TypeName::Bool }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.render() }
+    fn const_nval(&self) -> f64 { if self.0 { 1.0 } else { 0.0 } }
+    fn render(&self) -> String {
+        String::from(if self.0 { "IGAZ" } else { "HAMIS" })
+    }
+
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        var.type_name() == TypeName::Bool
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        match downcast::<BoolType>(var) {
+            Some(b) => Box::new(BoolType(b.0)),
+            None => self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> { Box::new(BoolType(false)) }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        match op {
+            UnaryOperator::Not => Some(self.init_val()),
+            _ => None
+        }
+    }
+
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        match op {
+            UnaryOperator::Not => Ok(Box::new(BoolType(!self.0))),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        if op == BinaryOperator::At {
+            return Some(TypeName::String.init_val());
+        }
+        if var.type_name() != TypeName::Bool {
+            return None;
+        }
+        match op {
+            BinaryOperator::And | BinaryOperator::Or |
+            BinaryOperator::Eq | BinaryOperator::Ne => Some(self.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        if op == BinaryOperator::At {
+            return Ok(concat(self, var));
+        }
+        let (a, b) = match downcast::<BoolType>(var) {
+            Some(b) => (self.0, b.0),
+            None => return Err(unsupported(op.op(), var))
+        };
+
+        match op {
+            BinaryOperator::And => Ok(Box::new(BoolType(a && b))),
+            BinaryOperator::Or => Ok(Box::new(BoolType(a || b))),
+            BinaryOperator::Eq => Ok(Box::new(BoolType(a == b))),
+            BinaryOperator::Ne => Ok(Box::new(BoolType(a != b))),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn parse_data(&self, s: &str) -> Option<Box<dyn Type>> {
+        match s.trim().to_lowercase().as_str() {
+            "igaz" => Some(Box::new(BoolType(true))),
+            "hamis" => Some(Box::new(BoolType(false))),
+            _ => None
+        }
+    }
+}
+
+impl ArrayType {
+    pub fn new(elem: &TypeName, size: usize) -> ArrayType {
+        let items = (0..size).map(|_| elem.init_val()).collect();
+        ArrayType { elem: elem.clone(), items }
+    }
+
+    /// Position in `items` of the element at index `x`.
+    fn position(&self, x: &dyn Type) -> Result<usize, RuntimeError> {
+        let i = match downcast::<IntType>(x) {
+            Some(i) => i.0,
+            None => return Err(unsupported(BinaryOperator::Bracket.op(), x))
+        };
+        if i < 1 || i as u64 > self.items.len() as u64 {
+            return Err(RuntimeError::Index(i, self.items.len()));
+        }
+        Ok(i as usize - 1)
+    }
+}
+
+/// Rows of a matrix, with the numbers aligned right and other values
+/// left in each column.
+fn print_table(rows: &[Box<dyn Type>], s: &mut String) {
+    let cells: Vec<Vec<(String, bool)>> = rows.iter()
+        .filter_map(|row| downcast::<ArrayType>(row.as_ref()))
+        .map(|row| row.items.iter().map(|v| {
+            let mut cell = String::new();
+            v.print_data(&mut cell);
+            (cell, as_float(v.as_ref()).is_some())
+        }).collect())
+        .collect();
+
+    let columns = cells.first().map_or(0, |row| row.len());
+    let widths: Vec<usize> = (0..columns)
+        .map(|j| cells.iter().map(|row| row[j].0.chars().count()).max().unwrap_or(0))
+        .collect();
+
+    for (i, row) in cells.iter().enumerate() {
+        if i > 0 {
+            s.push('\n');
+        }
+        let line: Vec<String> = row.iter().zip(&widths)
+            .map(|((cell, numeric), w)| if *numeric {
+                format!("{:>w$}", cell, w = w)
+            } else {
+                format!("{:<w$}", cell, w = w)
+            })
+            .collect();
+        s.push_str(line.join(" ").trim_end());
+    }
+}
+
+impl Type for ArrayType {
+    fn type_name(&self) -> TypeName {
+        TypeName::Array(Box::new(self.elem.clone()), self.items.len())
+    }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn const_sval(&self) -> String { self.render() }
+    fn const_nval(&self) -> f64 { 0.0 }
+    fn render(&self) -> String {
+        let items: Vec<String> = self.items.iter().map(|v| v.render()).collect();
+        format!("[{}]", items.join(", "))
+    }
+
+    /// Only arrays of the same size and element type can be assigned.
+    fn can_copy(&self, var: &dyn Type) -> bool {
+        var.type_name() == self.type_name()
+    }
+
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type> {
+        match downcast::<ArrayType>(var) {
+            Some(a) if self.can_copy(var) => {
+                Box::new(ArrayType { elem: a.elem.clone(), items: a.items.clone() })
+            },
+            _ => self.init_val()
+        }
+    }
+
+    fn init_val(&self) -> Box<dyn Type> {
+        Box::new(ArrayType::new(&self.elem, self.items.len()))
+    }
+
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>> {
+        match op {
+            UnaryOperator::Pipe => Some(TypeName::Int.init_val()),
+            _ => None
+        }
+    }
+
+    /// `|t|` is the number of elements.
+    fn unary_apply(&self, op: UnaryOperator) -> TypeResult {
+        match op {
+            UnaryOperator::Pipe => Ok(Box::new(IntType(self.items.len() as i64))),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>> {
+        match op {
+            BinaryOperator::At => Some(TypeName::String.init_val()),
+            BinaryOperator::Bracket if self.binary_has_accessor(op, var) => Some(self.elem.init_val()),
+            _ => None
+        }
+    }
+
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> TypeResult {
+        match op {
+            BinaryOperator::At => Ok(concat(self, var)),
+            BinaryOperator::Bracket => self.access(op, var),
+            _ => Err(unsupported(op.op(), self))
+        }
+    }
+
+    fn binary_has_accessor(&self, op: BinaryOperator, x: &dyn Type) -> bool {
+        op == BinaryOperator::Bracket && x.type_name() == TypeName::Int
+    }
+
+    fn access(&self, op: BinaryOperator, x: &dyn Type) -> TypeResult {
+        if op != BinaryOperator::Bracket {
+            return Err(unsupported(op.op(), self));
+        }
+        let i = self.position(x)?;
+        Ok(self.items[i].clone())
+    }
+
+    fn access_mut(&mut self, op: BinaryOperator, x: &dyn Type) -> Result<&mut Box<dyn Type>, RuntimeError> {
+        if op != BinaryOperator::Bracket {
+            return Err(unsupported(op.op(), self));
+        }
+        let i = self.position(x)?;
+        Ok(&mut self.items[i])
+    }
+
+    /// Elements separated by spaces. A matrix is printed as a table
+    /// with its columns aligned, more dimensions as tables one after
+    /// the other.
+    fn print_data(&self, s: &mut String) {
+        // a table starts on a line of its own so its columns line up
+        if matches!(self.elem, TypeName::Array(_, _)) && !s.is_empty() && !s.ends_with('\n') {
+            s.push('\n');
+        }
+        match &self.elem {
+            TypeName::Array(inner, _) if matches!(inner.as_ref(), TypeName::Array(_, _)) => {
+                for (i, v) in self.items.iter().enumerate() {
+                    if i > 0 {
+                        s.push_str("\n\n");
+                    }
+                    v.print_data(s);
+                }
+            },
+            TypeName::Array(_, _) => print_table(&self.items, s),
+            _ => for (i, v) in self.items.iter().enumerate() {
+                if i > 0 {
+                    s.push(' ');
+                }
+                v.print_data(s);
+            }
+        }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(a: &dyn Type, op: BinaryOperator, b: &dyn Type) -> String {
+        a.binary_apply(op, b).unwrap().const_sval()
+    }
+
+    #[test]
+    fn widening() {
+        let f = TypeName::Float.init_val();
+        assert!(f.can_copy(&IntType(3)));
+        assert_eq!(f.copy(&IntType(3)).const_nval(), 3.0);
+        assert_eq!(f.copy(&IntType(3)).type_name(), TypeName::Float);
+
+        let i = TypeName::Int.init_val();
+        assert!(!i.can_copy(&FloatType(3.0)));
+        assert!(!i.can_copy(&StringType(String::from("3"))));
+
+        let s = TypeName::String.init_val();
+        assert!(s.can_copy(&CharType('ő')));
+        assert_eq!(s.copy(&CharType('ő')).const_sval(), "ő");
+        assert!(!TypeName::Char.init_val().can_copy(s.as_ref()));
+    }
+
+    #[test]
+    fn mixed_arithmetic() {
+        assert_eq!(apply(&IntType(1), BinaryOperator::Plus, &FloatType(0.5)), "1.5");
+        assert_eq!(apply(&FloatType(0.5), BinaryOperator::Star, &IntType(4)), "2");
+        assert_eq!(apply(&IntType(7), BinaryOperator::Slash, &IntType(2)), "3.5");
+        assert_eq!(apply(&IntType(7), BinaryOperator::Div, &IntType(2)), "3");
+        assert_eq!(apply(&IntType(2), BinaryOperator::Lt, &FloatType(2.5)), "IGAZ");
+        assert_eq!(apply(&CharType('a'), BinaryOperator::Plus, &StringType(String::from("bc"))), "abc");
+
+        let t = IntType(1).binary_optype(BinaryOperator::Plus, &FloatType(0.0)).unwrap();
+        assert_eq!(t.type_name(), TypeName::Float);
+        assert!(IntType(1).binary_optype(BinaryOperator::Plus, &BoolType(true)).is_none());
+        assert_eq!(IntType(i64::MAX).binary_apply(BinaryOperator::Plus, &IntType(1)).err(),
+            Some(RuntimeError::Overflow));
+    }
+
+    /// `EGÉSZ ^ EGÉSZ` stays an EGÉSZ, so negative powers are errors.
+    #[test]
+    fn powers() {
+        let t = IntType(2).binary_optype(BinaryOperator::Hat, &IntType(-1)).unwrap();
+        assert_eq!(t.type_name(), TypeName::Int);
+        assert_eq!(apply(&IntType(2), BinaryOperator::Hat, &IntType(10)), "1024");
+        assert_eq!(IntType(2).binary_apply(BinaryOperator::Hat, &IntType(-1)).err(),
+            Some(RuntimeError::Domain("^")));
+        assert_eq!(IntType(0).binary_apply(BinaryOperator::Hat, &IntType(-1)).err(),
+            Some(RuntimeError::DivByZero));
+        assert_eq!(FloatType(0.0).binary_apply(BinaryOperator::Hat, &IntType(-2)).err(),
+            Some(RuntimeError::DivByZero));
+        assert_eq!(apply(&IntType(2), BinaryOperator::Hat, &FloatType(-1.0)), "0.5");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(FloatType(2.7).unary_apply(UnaryOperator::Trunc).unwrap().const_sval(), "2");
+        assert_eq!(FloatType(2.5).unary_apply(UnaryOperator::Round).unwrap().const_sval(), "3");
+        assert_eq!(IntType(-4).unary_apply(UnaryOperator::Pipe).unwrap().const_sval(), "4");
+        assert_eq!(IntType(2).unary_apply(UnaryOperator::Real).unwrap().type_name(), TypeName::Float);
+        assert_eq!(StringType(String::from("árvíz")).unary_apply(UnaryOperator::Pipe)
+            .unwrap().const_sval(), "5");
+        assert_eq!(StringType(String::from("árvíz")).unary_apply(UnaryOperator::Upper)
+            .unwrap().const_sval(), "ÁRVÍZ");
+        assert_eq!(CharType('7').unary_apply(UnaryOperator::IsNum).unwrap().const_sval(), "IGAZ");
+        assert_eq!(CharType('ű').unary_apply(UnaryOperator::IsAlpha).unwrap().const_sval(), "IGAZ");
+        assert_eq!(IntType(0).unary_apply(UnaryOperator::Log).err(), Some(RuntimeError::Domain("LOG")));
+        assert!(BoolType(true).unary_apply(UnaryOperator::Sin).is_err());
+    }
+
+    #[test]
+    fn input_data() {
+        assert_eq!(FloatType(0.0).parse_data("2,5").unwrap().const_nval(), 2.5);
+        assert_eq!(IntType(0).parse_data(" 42 ").unwrap().const_nval(), 42.0);
+        assert!(IntType(0).parse_data("4.2").is_none());
+        assert!(CharType(' ').parse_data("ab").is_none());
+        assert_eq!(BoolType(false).parse_data("Igaz").unwrap().const_sval(), "IGAZ");
+    }
+
+    #[test]
+    fn arrays() {
+        let mut t = ArrayType::new(&TypeName::Float, 3);
+        assert_eq!(t.unary_apply(UnaryOperator::Pipe).unwrap().const_sval(), "3");
+        *t.access_mut(BinaryOperator::Bracket, &IntType(2)).unwrap() = Box::new(FloatType(1.5));
+        assert_eq!(t.render(), "[0.0, 1.5, 0.0]");
+        assert_eq!(apply(&t, BinaryOperator::Bracket, &IntType(2)), "1.5");
+
+        assert_eq!(t.access(BinaryOperator::Bracket, &IntType(0)).err(), Some(RuntimeError::Index(0, 3)));
+        assert_eq!(t.access(BinaryOperator::Bracket, &IntType(4)).err(), Some(RuntimeError::Index(4, 3)));
+        assert!(!t.binary_has_accessor(BinaryOperator::Bracket, &FloatType(1.0)));
+        assert!(t.binary_optype(BinaryOperator::Bracket, &CharType('a')).is_none());
+
+        let u = TypeName::Array(Box::new(TypeName::Float), 3).init_val();
+        assert!(u.can_copy(&t));
+        assert!(!u.can_copy(&ArrayType::new(&TypeName::Float, 4)));
+        assert!(!u.can_copy(&ArrayType::new(&TypeName::Int, 3)));
+        let mut s = String::new();
+        u.copy(&t).print_data(&mut s);
+        assert_eq!(s, "0.0 1.5 0.0");
+    }
+
+    #[test]
+    fn matrix_data() {
+        let row = TypeName::Array(Box::new(TypeName::Int), 3);
+        let mut m = ArrayType::new(&row, 2);
+        for (i, j, v) in [(1, 1, 7), (1, 3, -12), (2, 2, 100)] {
+            let r = m.access_mut(BinaryOperator::Bracket, &IntType(i)).unwrap();
+            *r.access_mut(BinaryOperator::Bracket, &IntType(j)).unwrap() = Box::new(IntType(v));
+        }
+        let mut s = String::new();
+        m.print_data(&mut s);
+        assert_eq!(s, "7   0 -12\n0 100   0");
+
+        let mut s = String::from("1 4 9");
+        m.print_data(&mut s);
+        assert_eq!(s, "1 4 9\n7   0 -12\n0 100   0");
+
+        let mut names = ArrayType::new(&TypeName::Array(Box::new(TypeName::String), 2), 2);
+        *names.access_mut(BinaryOperator::Bracket, &IntType(1)).unwrap()
+            .access_mut(BinaryOperator::Bracket, &IntType(1)).unwrap() = Box::new(StringType(String::from("Éva")));
+        let mut s = String::new();
+        names.print_data(&mut s);
+        assert_eq!(s, "Éva\n");
+
+        let cube = ArrayType::new(&TypeName::Array(Box::new(row), 1), 2);
+        let mut s = String::new();
+        cube.print_data(&mut s);
+        assert_eq!(s, "0 0 0\n\n0 0 0");
+    }
+}
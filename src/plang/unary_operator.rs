@@ -1,4 +1,5 @@
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOperator {
     Minus, Sin, Cos, Tan,
     ArcSin, ArcCos, ArcTan,
@@ -9,6 +10,35 @@ pub enum UnaryOperator {
 }
 
 impl UnaryOperator {
+    /// Prefix operators bind tighter than `*`, but looser than `^`, so
+    /// `-a^b` is `-(a^b)`. `| |` encloses its operand, it needs no
+    /// precedence.
+    pub const PRECEDENCE: u8 = 6;
+
+    /// Looks up the operator written as a keyword, by its lexical 
+    /// (lowercase, deaccented) form.
+    pub fn from_lexical(lex: &str) -> Option<UnaryOperator> {
+        match lex {
+            "sin" => Some(UnaryOperator::Sin),
+            "cos" => Some(UnaryOperator::Cos),
+            "tan" => Some(UnaryOperator::Tan),
+            "arcsin" => Some(UnaryOperator::ArcSin),
+            "arccos" => Some(UnaryOperator::ArcCos),
+            "arctan" => Some(UnaryOperator::ArcTan),
+            "log" => Some(UnaryOperator::Log),
+            "nem" => Some(UnaryOperator::Not),
+            "exp" => Some(UnaryOperator::Exp),
+            "egesz" => Some(UnaryOperator::Trunc),
+            "kerek" => Some(UnaryOperator::Round),
+            "valos" => Some(UnaryOperator::Real),
+            "nagy" => Some(UnaryOperator::Upper),
+            "kis" => Some(UnaryOperator::Lower),
+            "szam" => Some(UnaryOperator::IsNum),
+            "betu" => Some(UnaryOperator::IsAlpha),
+            _ => None
+        }
+    }
+
     pub const fn op(&self) -> &'static str {
         match self {
             UnaryOperator::Minus => "-",
@@ -34,7 +64,7 @@ impl UnaryOperator {
 
     pub fn render(&self, e: String /* Expression */) -> String {
         match self {
-            UnaryOperator::Pipe => String::from("|") + self.op() + "|",
+            UnaryOperator::Pipe => String::from("|") + &e + "|",
             _ => String::from(self.op()) + if self.op().len() > 1 { " " } else { "" } + &e
         }
     }
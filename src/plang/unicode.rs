@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+
+/// Combining marks, the letters they go on, and the precomposed letters
+/// they make, in the same order. Only the letters of Latin-1 and Latin
+/// Extended-A, which covers Hungarian and its neighbours, not the whole
+/// Unicode table.
+const COMPOSITIONS: [(char, &str, &str); 13] = [
+    ('\u{300}', "AEIOUaeiou", "ÀÈÌÒÙàèìòù"), // grave
+    ('\u{301}', "AEIOUYaeiouyCcLlNnRrSsZz", "ÁÉÍÓÚÝáéíóúýĆćĹĺŃńŔŕŚśŹź"), // acute
+    ('\u{302}', "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"), // circumflex
+    ('\u{303}', "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"), // tilde
+    ('\u{304}', "AaEeIiOoUu", "ĀāĒēĪīŌōŪū"), // macron
+    ('\u{306}', "AaEeGgIiOoUu", "ĂăĔĕĞğĬĭŎŏŬŭ"), // breve
+    ('\u{307}', "CcEeGgIZz", "ĊċĖėĠġİŻż"), // dot above
+    ('\u{308}', "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"), // diaeresis
+    ('\u{30a}', "AaUu", "ÅåŮů"), // ring above
+    ('\u{30b}', "OoUu", "ŐőŰű"), // double acute
+    ('\u{30c}', "CcDdEeLlNnRrSsTtZz", "ČčĎďĚěĽľŇňŘřŠšŤťŽž"), // caron
+    ('\u{327}', "CcGgKkLlNnRrSsTt", "ÇçĢģĶķĻļŅņŖŗŞşŢţ"), // cedilla
+    ('\u{328}', "AaEeIiUu", "ĄąĘęĮįŲų"), // ogonek
+];
+
+/// Combining diacritical marks, the blocks accents are usually taken from.
+pub fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{36F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}'
+              | '\u{20D0}'..='\u{20FF}' | '\u{FE20}'..='\u{FE2F}')
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+    let (_, bases, composed) = COMPOSITIONS.iter().find(|(m, _, _)| *m == mark)?;
+    let i = bases.chars().position(|b| b == base)?;
+    composed.chars().nth(i)
+}
+
+/// `s` with letters followed by combining marks replaced by their
+/// precomposed form where there is one, like NFC does for the letters in
+/// the table. Borrows `s` if there's nothing to compose.
+pub fn nfc(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(is_combining_mark) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut result = String::with_capacity(s.len());
+    let mut last: Option<char> = None;
+    for c in s.chars() {
+        match last.and_then(|l| compose(l, c)) {
+            Some(composed) => last = Some(composed),
+            None => {
+                result.extend(last);
+                last = Some(c);
+            }
+        }
+    }
+    result.extend(last);
+    Cow::Owned(result)
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composition() {
+        assert!(matches!(nfc("összeg"), Cow::Borrowed("összeg")));
+        assert_eq!(nfc("o\u{308}sszeg"), "összeg");
+        assert_eq!(nfc("PROGRA\u{301}M"), "PROGRÁM");
+        assert_eq!(nfc("O\u{30B}u\u{30B}"), "Őű");
+        assert_eq!(nfc("s\u{30C}c\u{327}"), "šç");
+        // nothing to compose with
+        assert_eq!(nfc("\u{301}x\u{301}"), "\u{301}x\u{301}");
+    }
+}
@@ -1,100 +1,223 @@
+use std::any::Any;
+use std::fmt;
 use super::lexer::*;
+use super::tokenizer::{ Span, TokenType };
+use super::diagnostic::{ Diagnostic, Code, Message };
+use super::catalog::Phrase;
 use super::unary_operator::*;
 use super::binary_operator::*;
+use super::environment::RuntimeError;
+use super::types::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeName {
+    Int,
+    Float,
+    String,
+    Char,
+    Bool,
+    /// `TÖMB[size: TYPE]`, indexed from 1 to `size`. A matrix is an
+    /// array of arrays, `TÖMB[rows, columns: TYPE]`.
+    Array(Box<TypeName>, usize)
+}
+
+impl TypeName {
+    pub fn from_lexical(lex: &str) -> Option<TypeName> {
+        match lex {
+            "egesz" => Some(TypeName::Int),
+            "valos" => Some(TypeName::Float),
+            "szoveg" => Some(TypeName::String),
+            "karakter" => Some(TypeName::Char),
+            "logikai" => Some(TypeName::Bool),
+            _ => None
+        }
+    }
+
+    /// Whether a keyword starts a type, as `EGÉSZ` or `TÖMB` do.
+    pub fn is_type_word(lex: &str) -> bool {
+        lex == "tomb" || TypeName::from_lexical(lex).is_some()
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            TypeName::Int => String::from("EGÉSZ"),
+            TypeName::Float => String::from("VALÓS"),
+            TypeName::String => String::from("SZÖVEG"),
+            TypeName::Char => String::from("KARAKTER"),
+            TypeName::Bool => String::from("LOGIKAI"),
+            TypeName::Array(elem, size) => {
+                let mut sizes = vec![size.to_string()];
+                let mut elem = elem.as_ref();
+                while let TypeName::Array(inner, size) = elem {
+                    sizes.push(size.to_string());
+                    elem = inner;
+                }
+                format!("TÖMB[{}: {}]", sizes.join(", "), elem.name())
+            }
+        }
+    }
+
+    /// Parses a type keyword or `TÖMB[size, size...: TYPE]`. On failure
+    /// the lexer is left at the token that does not fit.
+    pub fn parse(lex: &mut Lexer) -> Result<TypeName, Diagnostic> {
+        if lex.at(TokenKind::Keyword("tomb")) {
+            lex.next();
+            lex.expect(TokenKind::Op("["))?;
+            let mut sizes = vec![TypeName::parse_size(lex)?];
+            while lex.at(TokenKind::Op(",")) {
+                lex.next();
+                sizes.push(TypeName::parse_size(lex)?);
+            }
+            lex.expect(TokenKind::Op(":"))?;
+            let mut t = TypeName::parse(lex)?;
+            lex.expect(TokenKind::Op("]"))?;
+            for size in sizes.into_iter().rev() {
+                t = TypeName::Array(Box::new(t), size);
+            }
+            return Ok(t);
+        }
+
+        let vtype = if lex.is_keyword() {
+            TypeName::from_lexical(&lex.lexical())
+        } else {
+            None
+        };
+        match vtype {
+            Some(t) => {
+                lex.next();
+                Ok(t)
+            },
+            None => Err(Diagnostic::error(Code::DeclType, lex.span(), &[]).with_arg(lex.describe()))
+        }
+    }
+
+    /// Arrays are indexed from 1, so their size is a positive literal.
+    fn parse_size(lex: &mut Lexer) -> Result<usize, Diagnostic> {
+        if lex.ttype() == TokenType::Int && lex.ival() > 0 {
+            let size = lex.ival() as usize;
+            lex.next();
+            return Ok(size);
+        }
+        Err(Diagnostic::error(Code::ArraySize, lex.span(), &[]).with_arg(lex.describe()))
+    }
 
-pub enum Error {
-    None,
-    Name,
-    Colon,
-    Type,
-    DupName
+    /// Default value of a freshly declared variable.
+    pub fn init_val(&self) -> Box<dyn Type> {
+        match self {
+            TypeName::Int => Box::new(IntType(0)),
+            TypeName::Float => Box::new(FloatType(0.0)),
+            TypeName::String => Box::new(StringType(String::new())),
+            TypeName::Char => Box::new(CharType(' ')),
+            TypeName::Bool => Box::new(BoolType(false)),
+            TypeName::Array(elem, size) => Box::new(ArrayType::new(elem, *size))
+        }
+    }
 }
 
+/// A PLang value. A value also stands for its own type: declarations
+/// store a prototype (see `TypeName::init_val`), which is asked whether 
+/// other values `can_copy` into it, and what type operators yield.
 pub trait Type {
+    fn type_name(&self) -> TypeName;
+    fn as_any(&self) -> &dyn Any;
+
     fn const_sval(&self) -> String;
     fn const_nval(&self) -> f64;
     fn render(&self) -> String;
-    fn can_copy(&self, var: Box<dyn Type>) -> bool;
-    fn copy(&self, var: Box<dyn Type>) -> Box<dyn Type>;
+    fn can_copy(&self, var: &dyn Type) -> bool;
+    fn copy(&self, var: &dyn Type) -> Box<dyn Type>;
     fn init_val(&self) -> Box<dyn Type>;
     
-    fn unary_optype(&self, op: UnaryOperator) -> Box<dyn Type>;
-    fn unary_apply(&self, var: Box<dyn Type>) -> Box<dyn Type>;
+    fn unary_optype(&self, op: UnaryOperator) -> Option<Box<dyn Type>>;
+    fn unary_apply(&self, op: UnaryOperator) -> Result<Box<dyn Type>, RuntimeError>;
+
+    fn binary_optype(&self, op: BinaryOperator, var: &dyn Type) -> Option<Box<dyn Type>>;
+    fn binary_apply(&self, op: BinaryOperator, var: &dyn Type) -> Result<Box<dyn Type>, RuntimeError>;
 
-    // I dunno about these
-    fn binary_optype(&self, op: BinaryOperator, var: Box<dyn Type>) -> Box<dyn Type>;
-    fn binary_apply(&self, op: BinaryOperator, t: Box<dyn Type>, var1: Box<dyn Type>, var2: Box<dyn Type>) -> Box<dyn Type>;
-    fn binary_has_accessor(&self, op: BinaryOperator, x: Box<dyn Type>) -> bool;
-    fn access(&self, op: BinaryOperator, a: Box<dyn Type>, b: Box<dyn Type>, c: Box<dyn Type>) -> Box<dyn Type>;
+    fn binary_has_accessor(&self, _op: BinaryOperator, _x: &dyn Type) -> bool { false }
+    fn access(&self, op: BinaryOperator, _x: &dyn Type) -> Result<Box<dyn Type>, RuntimeError> {
+        Err(RuntimeError::Operator(op.op(), self.type_name().name()))
+    }
+
+    /// The part of the value `access` reads, to assign to it.
+    fn access_mut(&mut self, op: BinaryOperator, _x: &dyn Type) -> Result<&mut Box<dyn Type>, RuntimeError> {
+        Err(RuntimeError::Operator(op.op(), self.type_name().name()))
+    }
 
+    /// Text written by `KI`.
     fn print_data(&self, s: &mut String) { s.push_str(&self.render())}
-}
 
-impl Type {
-    const fn null() -> NullType {
-        NullType
-    } 
+    /// Value of this type read by `BE`, `None` if `s` is not one.
+    fn parse_data(&self, _s: &str) -> Option<Box<dyn Type>> { None }
 }
 
-pub struct NullType;
-
-impl Type for NullType {
-    fn const_sval(&self) -> String { unimplemented!() }
-    fn const_nval(&self) -> f64 { unimplemented!() }
+impl Clone for Box<dyn Type> {
+    fn clone(&self) -> Self {
+        self.copy(self.as_ref())
+    }
+}
 
-    fn render(&self) -> String { unimplemented!() }
-    fn can_copy(&self, var: Box<dyn Type>) -> bool { unimplemented!() }
-    fn copy(&self, var: Box<dyn Type>) -> Box<dyn Type> { unimplemented!() }
-    fn init_val(&self) -> Box<dyn Type> { unimplemented!() }
-    
-    fn unary_optype(&self, op: UnaryOperator) -> Box<dyn Type> { unimplemented!() }
-    fn unary_apply(&self, var: Box<dyn Type>) -> Box<dyn Type> { unimplemented!() }
-
-    // I dunno about these
-    fn binary_optype(&self, op: BinaryOperator, var: Box<dyn Type>) -> Box<dyn Type> { unimplemented!() }
-    fn binary_apply(&self, op: BinaryOperator, t: Box<dyn Type>, var1: Box<dyn Type>, var2: Box<dyn Type>) -> Box<dyn Type> { unimplemented!() }
-    fn binary_has_accessor(&self, op: BinaryOperator, x: Box<dyn Type>) -> bool { unimplemented!() }
-    fn access(&self, op: BinaryOperator, a: Box<dyn Type>, b: Box<dyn Type>, c: Box<dyn Type>) -> Box<dyn Type> { unimplemented!() }
+impl fmt::Debug for dyn Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.type_name().name(), self.render())
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct VarDecl {
-    names: Vec<String>,
-    is_last: bool,
-    err: Error,
-    err_idx: i32,
-    vtype: Box<dyn Type>,
+    pub names: Vec<String>,
+    /// Where each of `names` appears in the source
+    pub spans: Vec<Span>,
+    pub is_last: bool,
+    pub vtype: TypeName,
+    /// Doc comment written before the declaration
+    pub doc: Option<String>
 }
 
 impl VarDecl {
-    fn new(names: Vec<String>, vtype: Box<dyn Type>, err: Error) -> VarDecl {
-        VarDecl { names, vtype, is_last: false, err, err_idx: -1 }
+    fn new(names: Vec<String>, spans: Vec<Span>, vtype: TypeName, doc: Option<String>) -> VarDecl {
+        VarDecl { names, spans, vtype, is_last: false, doc }
     }
 
-    fn parse(lex: &mut Lexer) -> VarDecl {
+    /// Parses `name {, name} : TYPE`. On failure the lexer is left at 
+    /// the token that does not fit.
+    pub fn parse(lex: &mut Lexer) -> Result<VarDecl, Diagnostic> {
         let mut names: Vec<String> = vec![];
+        let mut spans: Vec<Span> = vec![];
+        let doc = lex.docs();
 
         if !lex.is_ident() {
-            return VarDecl::new(names, Box::from(Type::null()), Error::Name);
+            return Err(VarDecl::error(Code::DeclName, lex));
         }
 
         names.push(lex.lexical());
+        spans.push(lex.span());
         lex.next();
-        while lex.sval() == "," {
+        while lex.at(TokenKind::Op(",")) {
             lex.next();
             if !lex.is_ident() {
-                return VarDecl::new(names, Box::from(Type::null()), Error::Name);
+                return Err(VarDecl::error(Code::DeclName, lex));
             }
             names.push(lex.lexical());
+            spans.push(lex.span());
             lex.next();
         }
 
-        if lex.sval() != ":" {
-            return VarDecl::new(names, Box::from(Type::null()), Error::Colon);
+        if !lex.at(TokenKind::Op(":")) {
+            return Err(VarDecl::error(Code::DeclColon, lex)
+                .with_label(Message::new(Phrase::ExpectedHere, "`:`"))
+                .with_note(Phrase::DeclSyntax));
         }
         lex.next();
 
-        // TODO parse type
+        match TypeName::parse(lex) {
+            Ok(t) => Ok(VarDecl::new(names, spans, t, doc)),
+            Err(d) if d.code == Code::DeclType => Err(d.with_note(Phrase::TypeList)),
+            Err(d) => Err(d)
+        }
+    }
 
-        return VarDecl::new(vec![], Box::from(Type::null()), Error::None);
+    fn error(code: Code, lex: &Lexer) -> Diagnostic {
+        Diagnostic::error(code, lex.span(), &[]).with_arg(lex.describe())
     }
-}
\ No newline at end of file
+}